@@ -0,0 +1,388 @@
+-- Schema of the api-server knex migrations up to 20221226053047_create_gasless_transactions.
+-- Tables and indexes use the names generated by knex and are only created if they don't exist,
+-- so databases already migrated by knex are adopted as they are.
+
+-- u32: bigint(i64)
+-- u64: decimal(20, 0)
+-- u128: decimal(40, 0)
+-- u256: decimal(80, 0)
+
+CREATE TABLE IF NOT EXISTS blocks (
+    number numeric NOT NULL PRIMARY KEY,
+    hash bytea NOT NULL CONSTRAINT blocks_hash_unique UNIQUE,
+    parent_hash bytea NOT NULL,
+    gas_limit numeric NOT NULL,
+    gas_used numeric NOT NULL,
+    miner bytea NOT NULL,
+    size integer NOT NULL,
+    timestamp timestamptz NOT NULL,
+    status text NOT NULL DEFAULT 'unfinalized' CHECK (status IN ('unfinalized', 'finalized', 'reverted')),
+    logs_bloom bytea
+);
+CREATE INDEX IF NOT EXISTS blocks_status_index ON blocks (status);
+
+CREATE TABLE IF NOT EXISTS transactions (
+    id bigserial PRIMARY KEY,
+    hash bytea NOT NULL CONSTRAINT transactions_hash_unique UNIQUE,
+    eth_tx_hash bytea NOT NULL CONSTRAINT transactions_eth_tx_hash_unique UNIQUE,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    transaction_index integer NOT NULL,
+    from_address bytea NOT NULL,
+    to_address bytea,
+    value numeric(80, 0) NOT NULL,
+    nonce bigint NOT NULL,
+    gas_limit numeric,
+    gas_price numeric,
+    input bytea,
+    v smallint NOT NULL,
+    r bytea NOT NULL,
+    s bytea NOT NULL,
+    cumulative_gas_used numeric,
+    gas_used numeric,
+    contract_address bytea,
+    exit_code smallint NOT NULL,
+    chain_id numeric,
+    status_code integer,
+    failure_reason text,
+    logs_bloom bytea,
+    type smallint NOT NULL DEFAULT 0,
+    access_list text,
+    max_priority_fee_per_gas numeric,
+    max_fee_per_gas numeric,
+    CONSTRAINT block_hash_transaction_index_idx UNIQUE (block_hash, transaction_index),
+    CONSTRAINT block_number_transaction_index_idx UNIQUE (block_number, transaction_index)
+);
+CREATE INDEX IF NOT EXISTS transactions_from_address_index ON transactions (from_address);
+CREATE INDEX IF NOT EXISTS transactions_to_address_index ON transactions (to_address);
+CREATE INDEX IF NOT EXISTS transactions_contract_address_index ON transactions (contract_address);
+
+CREATE TABLE IF NOT EXISTS logs (
+    id bigserial PRIMARY KEY,
+    transaction_id bigint NOT NULL,
+    transaction_hash bytea NOT NULL,
+    transaction_index integer NOT NULL,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    address bytea NOT NULL,
+    data bytea,
+    log_index integer NOT NULL,
+    topics bytea ARRAY NOT NULL
+);
+CREATE INDEX IF NOT EXISTS logs_transaction_id_index ON logs (transaction_id);
+CREATE INDEX IF NOT EXISTS logs_transaction_hash_index ON logs (transaction_hash);
+CREATE INDEX IF NOT EXISTS logs_block_number_index ON logs (block_number);
+CREATE INDEX IF NOT EXISTS logs_block_hash_index ON logs (block_hash);
+CREATE INDEX IF NOT EXISTS logs_address_index ON logs (address);
+
+CREATE TABLE IF NOT EXISTS orphaned_blocks (
+    id bigserial PRIMARY KEY,
+    number numeric NOT NULL,
+    hash bytea NOT NULL,
+    parent_hash bytea NOT NULL,
+    gas_limit numeric NOT NULL,
+    gas_used numeric NOT NULL,
+    miner bytea NOT NULL,
+    size integer NOT NULL,
+    timestamp timestamptz NOT NULL,
+    replaced_by bytea,
+    orphaned_at timestamptz NOT NULL,
+    logs_bloom bytea
+);
+CREATE INDEX IF NOT EXISTS orphaned_blocks_number_index ON orphaned_blocks (number);
+CREATE INDEX IF NOT EXISTS orphaned_blocks_hash_index ON orphaned_blocks (hash);
+CREATE INDEX IF NOT EXISTS orphaned_blocks_orphaned_at_index ON orphaned_blocks (orphaned_at);
+
+CREATE TABLE IF NOT EXISTS orphaned_transactions (
+    id bigserial PRIMARY KEY,
+    hash bytea NOT NULL,
+    eth_tx_hash bytea NOT NULL,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    transaction_index integer NOT NULL,
+    from_address bytea NOT NULL,
+    to_address bytea,
+    value numeric(80, 0) NOT NULL,
+    nonce bigint NOT NULL,
+    gas_limit numeric,
+    gas_price numeric,
+    input bytea,
+    v smallint NOT NULL,
+    r bytea NOT NULL,
+    s bytea NOT NULL,
+    cumulative_gas_used numeric,
+    gas_used numeric,
+    contract_address bytea,
+    exit_code smallint NOT NULL,
+    chain_id numeric,
+    replaced_by bytea,
+    orphaned_at timestamptz NOT NULL,
+    status_code integer,
+    failure_reason text,
+    logs_bloom bytea,
+    type smallint NOT NULL DEFAULT 0,
+    access_list text,
+    max_priority_fee_per_gas numeric,
+    max_fee_per_gas numeric
+);
+CREATE INDEX IF NOT EXISTS orphaned_transactions_hash_index ON orphaned_transactions (hash);
+CREATE INDEX IF NOT EXISTS orphaned_transactions_eth_tx_hash_index ON orphaned_transactions (eth_tx_hash);
+CREATE INDEX IF NOT EXISTS orphaned_transactions_block_number_index ON orphaned_transactions (block_number);
+CREATE INDEX IF NOT EXISTS orphaned_transactions_orphaned_at_index ON orphaned_transactions (orphaned_at);
+
+CREATE TABLE IF NOT EXISTS orphaned_logs (
+    id bigserial PRIMARY KEY,
+    transaction_hash bytea NOT NULL,
+    transaction_index integer NOT NULL,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    address bytea NOT NULL,
+    data bytea,
+    log_index integer NOT NULL,
+    topics bytea ARRAY NOT NULL,
+    replaced_by bytea,
+    orphaned_at timestamptz NOT NULL
+);
+CREATE INDEX IF NOT EXISTS orphaned_logs_transaction_hash_index ON orphaned_logs (transaction_hash);
+CREATE INDEX IF NOT EXISTS orphaned_logs_block_number_index ON orphaned_logs (block_number);
+CREATE INDEX IF NOT EXISTS orphaned_logs_orphaned_at_index ON orphaned_logs (orphaned_at);
+
+CREATE TABLE IF NOT EXISTS accounts (
+    id bigint PRIMARY KEY,
+    script_hash bytea NOT NULL CONSTRAINT accounts_script_hash_unique UNIQUE,
+    script bytea NOT NULL,
+    first_seen_block_number numeric NOT NULL,
+    code_hash bytea NOT NULL,
+    type text NOT NULL CHECK (type IN ('eth_eoa', 'polyjuice_contract', 'sudt', 'other')),
+    eth_address bytea
+);
+CREATE INDEX IF NOT EXISTS accounts_first_seen_block_number_index ON accounts (first_seen_block_number);
+CREATE INDEX IF NOT EXISTS accounts_type_index ON accounts (type);
+CREATE INDEX IF NOT EXISTS accounts_eth_address_index ON accounts (eth_address);
+
+CREATE TABLE IF NOT EXISTS token_transfers (
+    id bigserial PRIMARY KEY,
+    transaction_hash bytea NOT NULL,
+    transaction_index integer NOT NULL,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    transfer_index integer NOT NULL,
+    sudt_id bigint NOT NULL,
+    from_registry_id bigint NOT NULL,
+    from_address bytea NOT NULL,
+    to_registry_id bigint NOT NULL,
+    to_address bytea NOT NULL,
+    amount numeric(80, 0) NOT NULL,
+    kind text NOT NULL CHECK (kind IN ('transfer', 'fee')),
+    CONSTRAINT token_transfers_block_number_transfer_index_unique UNIQUE (block_number, transfer_index)
+);
+CREATE INDEX IF NOT EXISTS token_transfers_transaction_hash_index ON token_transfers (transaction_hash);
+CREATE INDEX IF NOT EXISTS token_transfers_block_number_index ON token_transfers (block_number);
+CREATE INDEX IF NOT EXISTS token_transfers_sudt_id_index ON token_transfers (sudt_id);
+CREATE INDEX IF NOT EXISTS token_transfers_from_address_index ON token_transfers (from_address);
+CREATE INDEX IF NOT EXISTS token_transfers_to_address_index ON token_transfers (to_address);
+
+CREATE TABLE IF NOT EXISTS orphaned_token_transfers (
+    id bigserial PRIMARY KEY,
+    transaction_hash bytea NOT NULL,
+    transaction_index integer NOT NULL,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    transfer_index integer NOT NULL,
+    sudt_id bigint NOT NULL,
+    from_registry_id bigint NOT NULL,
+    from_address bytea NOT NULL,
+    to_registry_id bigint NOT NULL,
+    to_address bytea NOT NULL,
+    amount numeric(80, 0) NOT NULL,
+    kind text NOT NULL CHECK (kind IN ('transfer', 'fee')),
+    replaced_by bytea,
+    orphaned_at timestamptz NOT NULL
+);
+CREATE INDEX IF NOT EXISTS orphaned_token_transfers_transaction_hash_index ON orphaned_token_transfers (transaction_hash);
+CREATE INDEX IF NOT EXISTS orphaned_token_transfers_block_number_index ON orphaned_token_transfers (block_number);
+CREATE INDEX IF NOT EXISTS orphaned_token_transfers_orphaned_at_index ON orphaned_token_transfers (orphaned_at);
+
+CREATE TABLE IF NOT EXISTS erc_transfers (
+    id bigserial PRIMARY KEY,
+    transaction_hash bytea NOT NULL,
+    transaction_index integer NOT NULL,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    log_index integer NOT NULL,
+    batch_index integer NOT NULL,
+    standard text NOT NULL CHECK (standard IN ('erc20', 'erc721', 'erc1155')),
+    token_contract bytea NOT NULL,
+    operator bytea,
+    from_address bytea NOT NULL,
+    to_address bytea NOT NULL,
+    token_id numeric(80, 0),
+    amount numeric(80, 0) NOT NULL,
+    CONSTRAINT erc_transfers_block_number_log_index_batch_index_unique UNIQUE (block_number, log_index, batch_index)
+);
+CREATE INDEX IF NOT EXISTS erc_transfers_transaction_hash_index ON erc_transfers (transaction_hash);
+CREATE INDEX IF NOT EXISTS erc_transfers_block_number_index ON erc_transfers (block_number);
+CREATE INDEX IF NOT EXISTS erc_transfers_token_contract_index ON erc_transfers (token_contract);
+CREATE INDEX IF NOT EXISTS erc_transfers_from_address_index ON erc_transfers (from_address);
+CREATE INDEX IF NOT EXISTS erc_transfers_to_address_index ON erc_transfers (to_address);
+
+CREATE TABLE IF NOT EXISTS erc_approvals (
+    id bigserial PRIMARY KEY,
+    transaction_hash bytea NOT NULL,
+    transaction_index integer NOT NULL,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    log_index integer NOT NULL,
+    standard text NOT NULL CHECK (standard IN ('erc20', 'erc721')),
+    token_contract bytea NOT NULL,
+    owner bytea NOT NULL,
+    spender bytea NOT NULL,
+    token_id numeric(80, 0),
+    amount numeric(80, 0),
+    CONSTRAINT erc_approvals_block_number_log_index_unique UNIQUE (block_number, log_index)
+);
+CREATE INDEX IF NOT EXISTS erc_approvals_transaction_hash_index ON erc_approvals (transaction_hash);
+CREATE INDEX IF NOT EXISTS erc_approvals_block_number_index ON erc_approvals (block_number);
+CREATE INDEX IF NOT EXISTS erc_approvals_token_contract_index ON erc_approvals (token_contract);
+CREATE INDEX IF NOT EXISTS erc_approvals_owner_index ON erc_approvals (owner);
+CREATE INDEX IF NOT EXISTS erc_approvals_spender_index ON erc_approvals (spender);
+
+CREATE TABLE IF NOT EXISTS withdrawals (
+    id bigserial PRIMARY KEY,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    withdrawal_index integer NOT NULL,
+    account_script_hash bytea NOT NULL,
+    registry_id bigint NOT NULL,
+    registry_address bytea,
+    capacity numeric(20, 0) NOT NULL,
+    sudt_script_hash bytea NOT NULL,
+    amount numeric(40, 0) NOT NULL,
+    owner_lock_hash bytea NOT NULL,
+    nonce bigint NOT NULL,
+    fee numeric(40, 0) NOT NULL,
+    chain_id numeric(20, 0) NOT NULL,
+    CONSTRAINT withdrawals_block_number_withdrawal_index_unique UNIQUE (block_number, withdrawal_index)
+);
+CREATE INDEX IF NOT EXISTS withdrawals_block_number_index ON withdrawals (block_number);
+CREATE INDEX IF NOT EXISTS withdrawals_account_script_hash_index ON withdrawals (account_script_hash);
+CREATE INDEX IF NOT EXISTS withdrawals_registry_address_index ON withdrawals (registry_address);
+CREATE INDEX IF NOT EXISTS withdrawals_owner_lock_hash_index ON withdrawals (owner_lock_hash);
+
+CREATE TABLE IF NOT EXISTS orphaned_withdrawals (
+    id bigserial PRIMARY KEY,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    withdrawal_index integer NOT NULL,
+    account_script_hash bytea NOT NULL,
+    registry_id bigint NOT NULL,
+    registry_address bytea,
+    capacity numeric(20, 0) NOT NULL,
+    sudt_script_hash bytea NOT NULL,
+    amount numeric(40, 0) NOT NULL,
+    owner_lock_hash bytea NOT NULL,
+    nonce bigint NOT NULL,
+    fee numeric(40, 0) NOT NULL,
+    chain_id numeric(20, 0) NOT NULL,
+    replaced_by bytea,
+    orphaned_at timestamptz NOT NULL
+);
+CREATE INDEX IF NOT EXISTS orphaned_withdrawals_block_number_index ON orphaned_withdrawals (block_number);
+CREATE INDEX IF NOT EXISTS orphaned_withdrawals_orphaned_at_index ON orphaned_withdrawals (orphaned_at);
+
+CREATE TABLE IF NOT EXISTS anomalies (
+    id bigserial PRIMARY KEY,
+    kind text NOT NULL CHECK (kind IN ('invalid_signature', 'signer_mismatch')),
+    transaction_hash bytea NOT NULL,
+    eth_tx_hash bytea NOT NULL,
+    block_number numeric NOT NULL,
+    transaction_index integer NOT NULL,
+    from_address bytea NOT NULL,
+    recovered_address bytea,
+    detail text,
+    detected_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS anomalies_transaction_hash_index ON anomalies (transaction_hash);
+CREATE INDEX IF NOT EXISTS anomalies_block_number_index ON anomalies (block_number);
+
+CREATE TABLE IF NOT EXISTS gw_transactions (
+    id bigserial PRIMARY KEY,
+    hash bytea NOT NULL CONSTRAINT gw_transactions_hash_unique UNIQUE,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    l2_tx_index integer NOT NULL,
+    from_id bigint NOT NULL,
+    to_id bigint NOT NULL,
+    nonce bigint NOT NULL,
+    args bytea NOT NULL,
+    from_lock_hash bytea NOT NULL,
+    eoa_type text,
+    exit_code smallint NOT NULL
+);
+CREATE INDEX IF NOT EXISTS gw_transactions_block_number_index ON gw_transactions (block_number);
+CREATE INDEX IF NOT EXISTS gw_transactions_from_id_index ON gw_transactions (from_id);
+
+CREATE TABLE IF NOT EXISTS orphaned_gw_transactions (
+    id bigserial PRIMARY KEY,
+    hash bytea NOT NULL,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    l2_tx_index integer NOT NULL,
+    from_id bigint NOT NULL,
+    to_id bigint NOT NULL,
+    nonce bigint NOT NULL,
+    args bytea NOT NULL,
+    from_lock_hash bytea NOT NULL,
+    eoa_type text,
+    exit_code smallint NOT NULL,
+    replaced_by bytea,
+    orphaned_at timestamptz NOT NULL
+);
+CREATE INDEX IF NOT EXISTS orphaned_gw_transactions_hash_index ON orphaned_gw_transactions (hash);
+CREATE INDEX IF NOT EXISTS orphaned_gw_transactions_block_number_index ON orphaned_gw_transactions (block_number);
+CREATE INDEX IF NOT EXISTS orphaned_gw_transactions_orphaned_at_index ON orphaned_gw_transactions (orphaned_at);
+
+CREATE TABLE IF NOT EXISTS gasless_transactions (
+    id bigserial PRIMARY KEY,
+    transaction_hash bytea NOT NULL CONSTRAINT gasless_transactions_transaction_hash_unique UNIQUE,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    transaction_index integer NOT NULL,
+    sender bytea NOT NULL,
+    entrypoint bytea NOT NULL,
+    call_contract bytea NOT NULL,
+    call_data bytea NOT NULL,
+    call_gas_limit numeric(80, 0) NOT NULL,
+    verification_gas_limit numeric(80, 0) NOT NULL,
+    max_fee_per_gas numeric(80, 0) NOT NULL,
+    max_priority_fee_per_gas numeric(80, 0) NOT NULL,
+    paymaster bytea,
+    paymaster_and_data bytea NOT NULL
+);
+CREATE INDEX IF NOT EXISTS gasless_transactions_block_number_index ON gasless_transactions (block_number);
+CREATE INDEX IF NOT EXISTS gasless_transactions_sender_index ON gasless_transactions (sender);
+CREATE INDEX IF NOT EXISTS gasless_transactions_call_contract_index ON gasless_transactions (call_contract);
+CREATE INDEX IF NOT EXISTS gasless_transactions_paymaster_index ON gasless_transactions (paymaster);
+
+CREATE TABLE IF NOT EXISTS orphaned_gasless_transactions (
+    id bigserial PRIMARY KEY,
+    transaction_hash bytea NOT NULL,
+    block_number numeric NOT NULL,
+    block_hash bytea NOT NULL,
+    transaction_index integer NOT NULL,
+    sender bytea NOT NULL,
+    entrypoint bytea NOT NULL,
+    call_contract bytea NOT NULL,
+    call_data bytea NOT NULL,
+    call_gas_limit numeric(80, 0) NOT NULL,
+    verification_gas_limit numeric(80, 0) NOT NULL,
+    max_fee_per_gas numeric(80, 0) NOT NULL,
+    max_priority_fee_per_gas numeric(80, 0) NOT NULL,
+    paymaster bytea,
+    paymaster_and_data bytea NOT NULL,
+    replaced_by bytea,
+    orphaned_at timestamptz NOT NULL
+);
+CREATE INDEX IF NOT EXISTS orphaned_gasless_transactions_transaction_hash_index ON orphaned_gasless_transactions (transaction_hash);
+CREATE INDEX IF NOT EXISTS orphaned_gasless_transactions_block_number_index ON orphaned_gasless_transactions (block_number);
+CREATE INDEX IF NOT EXISTS orphaned_gasless_transactions_orphaned_at_index ON orphaned_gasless_transactions (orphaned_at);
@@ -0,0 +1,12 @@
+-- Same as the knex migration 20221230021458_create_chain_metadata.
+
+CREATE TABLE IF NOT EXISTS chain_metadata (
+    id smallint PRIMARY KEY,
+    rollup_type_hash bytea NOT NULL,
+    chain_id numeric(20, 0) NOT NULL,
+    genesis_block_hash bytea NOT NULL,
+    l2_sudt_type_script_hash bytea NOT NULL,
+    polyjuice_type_script_hash bytea NOT NULL,
+    eth_account_lock_hash bytea NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
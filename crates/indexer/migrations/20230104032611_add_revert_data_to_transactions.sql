@@ -0,0 +1,4 @@
+-- Same as the knex migration 20230104032611_add_revert_data_to_transactions.
+
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS revert_data bytea;
+ALTER TABLE orphaned_transactions ADD COLUMN IF NOT EXISTS revert_data bytea;
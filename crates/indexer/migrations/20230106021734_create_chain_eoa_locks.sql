@@ -0,0 +1,7 @@
+-- Same as the knex migration 20230106021734_create_chain_eoa_locks.
+
+CREATE TABLE IF NOT EXISTS chain_eoa_locks (
+    eoa_type text PRIMARY KEY,
+    type_hash bytea NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,158 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+use anyhow::{anyhow, Result};
+use gw_types::{packed::Script, prelude::*};
+use lru::LruCache;
+use rust_decimal::Decimal;
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::pool::POOL;
+
+// Account scripts are immutable once created, so account id -> script can be cached across blocks.
+pub struct AccountCache {
+    scripts: Mutex<LruCache<u32, Script>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl AccountCache {
+    pub fn new(capacity: usize) -> Self {
+        AccountCache {
+            scripts: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, account_id: u32) -> Option<Script> {
+        self.lock().get(&account_id).cloned()
+    }
+
+    pub fn put(&self, account_id: u32, script: Script) {
+        self.lock().put(account_id, script);
+    }
+
+    pub fn remove(&self, account_id: u32) {
+        self.lock().pop(&account_id);
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn record(&self, hits: u64, misses: u64) {
+        self.hits.fetch_add(hits, Ordering::Relaxed);
+        self.misses.fetch_add(misses, Ordering::Relaxed);
+    }
+
+    // Returns (hits, misses), a miss means the script was requested from godwoken.
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<u32, Script>> {
+        // The cache is always consistent, ignore poisoning
+        self.scripts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountType {
+    EthEoa,
+    PolyjuiceContract,
+    Sudt,
+    Other,
+}
+
+impl AccountType {
+    // Value of `accounts.type`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountType::EthEoa => "eth_eoa",
+            AccountType::PolyjuiceContract => "polyjuice_contract",
+            AccountType::Sudt => "sudt",
+            AccountType::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub id: u32,
+    pub script_hash: [u8; 32],
+    pub code_hash: [u8; 32],
+    pub account_type: AccountType,
+    pub eth_address: Option<[u8; 20]>,
+    pub script: Script,
+}
+
+pub async fn query_account_scripts(account_ids: &[u32]) -> Result<Vec<(u32, Script)>> {
+    if account_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let ids = account_ids.iter().map(|id| *id as i64).collect::<Vec<_>>();
+    let rows: Vec<(i64, Vec<u8>)> =
+        sqlx::query_as("SELECT id, script FROM accounts WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(&*POOL)
+            .await?;
+
+    rows.into_iter()
+        .map(|(id, script)| {
+            let script = Script::from_slice(&script)
+                .map_err(|err| anyhow!("invalid script of account {}: {}", id, err))?;
+            Ok((id as u32, script))
+        })
+        .collect()
+}
+
+// Accounts already in the table are skipped.
+pub async fn insert_accounts(
+    accounts: &[Account],
+    first_seen_block_number: u64,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<()> {
+    if accounts.is_empty() {
+        return Ok(());
+    }
+
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+        "INSERT INTO accounts (id, script_hash, code_hash, type, eth_address, script, first_seen_block_number) ",
+    );
+    query_builder
+        .push_values(accounts, |mut b, account| {
+            b.push_bind(account.id as i64)
+                .push_bind(account.script_hash.to_vec())
+                .push_bind(account.code_hash.to_vec())
+                .push_bind(account.account_type.as_str())
+                .push_bind(account.eth_address.map(|address| address.to_vec()))
+                .push_bind(account.script.as_slice().to_vec())
+                .push_bind(Decimal::from(first_seen_block_number));
+        })
+        .push(" ON CONFLICT (id) DO NOTHING");
+    query_builder.build().execute(pg_tx).await?;
+
+    Ok(())
+}
+
+// Accounts first seen in rolled back blocks may be assigned to other scripts on the new chain,
+// returns the number of deleted accounts.
+pub async fn delete_accounts_from(
+    start_block_number: u64,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM accounts WHERE first_seen_block_number >= $1")
+        .bind(Decimal::from(start_block_number))
+        .execute(pg_tx)
+        .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use rayon::prelude::*;
+use rust_decimal::Decimal;
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::{
+    signature::recover_signer,
+    types::{Transaction, TransactionWithLogs},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    // the signature can't be recovered, e.g. it is not 65 bytes long
+    InvalidSignature,
+    // the signature recovers to an address other than from_address
+    SignerMismatch,
+}
+
+impl AnomalyKind {
+    // Value of `anomalies.kind`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyKind::InvalidSignature => "invalid_signature",
+            AnomalyKind::SignerMismatch => "signer_mismatch",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    pub transaction_hash: gw_common::H256,
+    pub eth_tx_hash: gw_common::H256,
+    pub block_number: u64,
+    pub transaction_index: u32,
+    pub from_address: [u8; 20],
+    pub recovered_address: Option<[u8; 20]>,
+    pub detail: Option<String>,
+}
+
+// Check the reconstructed eth transaction is signed by `from_address`.
+// Only polyjuice transactions are signed over the eth transaction, others are skipped.
+pub fn check_signature(tx: &Transaction) -> Option<Anomaly> {
+    tx.chain_id?;
+
+    let (kind, recovered_address, detail) = match recover_signer(tx) {
+        Ok(address) if address == tx.from_address => return None,
+        Ok(address) => (AnomalyKind::SignerMismatch, Some(address), None),
+        Err(err) => (AnomalyKind::InvalidSignature, None, Some(err.to_string())),
+    };
+    Some(Anomaly {
+        kind,
+        transaction_hash: tx.gw_tx_hash,
+        eth_tx_hash: tx.compute_eth_tx_hash(),
+        block_number: tx.block_number,
+        transaction_index: tx.transaction_index,
+        from_address: tx.from_address,
+        recovered_address,
+        detail,
+    })
+}
+
+pub fn check_signatures(txs: &[TransactionWithLogs]) -> Vec<Anomaly> {
+    txs.par_iter()
+        .filter_map(|tx| check_signature(&tx.tx))
+        .collect()
+}
+
+pub async fn insert_anomalies(
+    anomalies: Vec<Anomaly>,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<usize> {
+    if anomalies.is_empty() {
+        return Ok(0);
+    }
+    let anomalies_len = anomalies.len();
+
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+        "INSERT INTO anomalies (kind, transaction_hash, eth_tx_hash, block_number, transaction_index, from_address, recovered_address, detail) ",
+    );
+    query_builder.push_values(anomalies, |mut b, anomaly| {
+        b.push_bind(anomaly.kind.as_str())
+            .push_bind(anomaly.transaction_hash.as_slice().to_vec())
+            .push_bind(anomaly.eth_tx_hash.as_slice().to_vec())
+            .push_bind(Decimal::from(anomaly.block_number))
+            .push_bind(i64::from(anomaly.transaction_index))
+            .push_bind(anomaly.from_address.to_vec())
+            .push_bind(anomaly.recovered_address.map(|addr| addr.to_vec()))
+            .push_bind(anomaly.detail);
+    });
+    query_builder.build().execute(pg_tx).await?;
+
+    Ok(anomalies_len)
+}
+
+pub async fn delete_anomalies(
+    block_number: u64,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<()> {
+    sqlx::query("DELETE FROM anomalies WHERE block_number = $1")
+        .bind(Decimal::from(block_number))
+        .execute(pg_tx)
+        .await?;
+    Ok(())
+}
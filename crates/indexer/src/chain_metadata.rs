@@ -0,0 +1,536 @@
+use std::{collections::BTreeMap, future::Future, time::Duration};
+
+use anyhow::{anyhow, Result};
+use ckb_types::H256;
+use gw_web3_rpc_client::{error::RpcClientError, godwoken_async_client::GodwokenAsyncClient};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use sqlx::PgPool;
+
+use crate::config::{eoa_locks, ChainSpec, EoaLockConfig, IndexerConfig};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// Identity of the chain indexed into the database, stored in the single row of `chain_metadata`,
+// and the EOA locks in `chain_eoa_locks`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainMetadata {
+    pub chain_spec: ChainSpec,
+    pub genesis_block_hash: H256,
+}
+
+impl ChainMetadata {
+    // Returns (field, self, other) of the fields which differ
+    fn diff(&self, other: &ChainMetadata) -> Vec<(&'static str, String, String)> {
+        let mut diffs = self.chain_spec.diff(&other.chain_spec);
+        if self.genesis_block_hash != other.genesis_block_hash {
+            diffs.push((
+                "genesis_block_hash",
+                format!("0x{}", self.genesis_block_hash),
+                format!("0x{}", other.genesis_block_hash),
+            ));
+        }
+        diffs
+    }
+}
+
+// The chain indexed by this process, resolved at startup by `load_chain`
+#[derive(Debug, Clone)]
+pub struct Chain {
+    pub chain_spec: ChainSpec,
+    /// EOA locks of the rollup, txs from other or hidden locks are stored in gw_transactions
+    pub eoa_locks: Vec<EoaLockConfig>,
+    /// Where the chain spec comes from, "configuration file", "database" or "godwoken"
+    pub source: &'static str,
+    /// Whether the chain spec has been checked against godwoken, see `verify_chain`
+    pub verified: bool,
+}
+
+pub async fn load_chain_metadata(pool: &PgPool) -> Result<Option<ChainMetadata>> {
+    let row: Option<(Vec<u8>, Decimal, Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>)> = sqlx::query_as(
+        "SELECT rollup_type_hash, chain_id, genesis_block_hash, l2_sudt_type_script_hash, polyjuice_type_script_hash, eth_account_lock_hash FROM chain_metadata WHERE id = 1",
+    )
+    .fetch_optional(pool)
+    .await?;
+    let (
+        rollup_type_hash,
+        chain_id,
+        genesis_block_hash,
+        l2_sudt_type_script_hash,
+        polyjuice_type_script_hash,
+        eth_account_lock_hash,
+    ) = match row {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let eoa_locks: Vec<(String, Vec<u8>)> =
+        sqlx::query_as("SELECT eoa_type, type_hash FROM chain_eoa_locks")
+            .fetch_all(pool)
+            .await?;
+    // recorded before chain_eoa_locks existed
+    let eoa_lock_hashes = if eoa_locks.is_empty() {
+        None
+    } else {
+        let eoa_lock_hashes = eoa_locks
+            .into_iter()
+            .map(|(eoa_type, type_hash)| Ok((eoa_type, H256::from_slice(&type_hash)?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        Some(eoa_lock_hashes)
+    };
+    let chain_spec = ChainSpec {
+        rollup_type_hash: H256::from_slice(&rollup_type_hash)?,
+        chain_id: chain_id
+            .to_u64()
+            .ok_or_else(|| anyhow!("invalid chain_id {} in chain_metadata", chain_id))?,
+        l2_sudt_type_script_hash: H256::from_slice(&l2_sudt_type_script_hash)?,
+        polyjuice_type_script_hash: H256::from_slice(&polyjuice_type_script_hash)?,
+        eth_account_lock_hash: H256::from_slice(&eth_account_lock_hash)?,
+        eoa_lock_hashes,
+    };
+    Ok(Some(ChainMetadata {
+        chain_spec,
+        genesis_block_hash: H256::from_slice(&genesis_block_hash)?,
+    }))
+}
+
+// Insert the metadata and EOA locks unless rows exist, e.g. written by another indexer starting at
+// the same time.
+async fn insert_chain_metadata(pool: &PgPool, metadata: &ChainMetadata) -> Result<()> {
+    let chain_spec = &metadata.chain_spec;
+    let mut pg_tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO chain_metadata (id, rollup_type_hash, chain_id, genesis_block_hash, l2_sudt_type_script_hash, polyjuice_type_script_hash, eth_account_lock_hash) VALUES (1, $1, $2, $3, $4, $5, $6) ON CONFLICT (id) DO NOTHING",
+    )
+    .bind(chain_spec.rollup_type_hash.as_bytes())
+    .bind(Decimal::from(chain_spec.chain_id))
+    .bind(metadata.genesis_block_hash.as_bytes())
+    .bind(chain_spec.l2_sudt_type_script_hash.as_bytes())
+    .bind(chain_spec.polyjuice_type_script_hash.as_bytes())
+    .bind(chain_spec.eth_account_lock_hash.as_bytes())
+    .execute(&mut pg_tx)
+    .await?;
+    for (eoa_type, type_hash) in chain_spec.eoa_lock_hashes.iter().flatten() {
+        sqlx::query(
+            "INSERT INTO chain_eoa_locks (eoa_type, type_hash) VALUES ($1, $2) ON CONFLICT (eoa_type) DO NOTHING",
+        )
+        .bind(eoa_type)
+        .bind(type_hash.as_bytes())
+        .execute(&mut pg_tx)
+        .await?;
+    }
+    pg_tx.commit().await?;
+    Ok(())
+}
+
+// Resolve the chain spec and bind the database to it.
+//
+// The chain spec comes from the configuration file, or `chain_metadata` recorded by an earlier run.
+// If it lists the EOA locks, godwoken is not requested and the chain is verified later by
+// `verify_chain`, so the indexer can start before the node. Otherwise, e.g. on the very first run,
+// godwoken is requested until it is reachable.
+pub async fn load_chain(pool: &PgPool, config: &IndexerConfig) -> Result<Chain> {
+    let db_metadata = load_chain_metadata(pool).await?;
+    if let (Some(configured), Some(db_metadata)) = (&config.chain_spec, &db_metadata) {
+        ensure_same_chain(
+            ("configuration file", configured),
+            ("database", &db_metadata.chain_spec),
+        )?;
+    }
+    let chain_spec = match (&config.chain_spec, &db_metadata) {
+        (Some(configured), db_metadata) => {
+            let mut chain_spec = configured.clone();
+            // same chain as the database, checked above
+            if chain_spec.eoa_lock_hashes.is_none() {
+                chain_spec.eoa_lock_hashes = db_metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.chain_spec.eoa_lock_hashes.clone());
+            }
+            Some(("configuration file", chain_spec))
+        }
+        (None, Some(db_metadata)) => Some(("database", db_metadata.chain_spec.clone())),
+        (None, None) => None,
+    };
+
+    if let Some((source, chain_spec)) = &chain_spec {
+        if let Some(eoa_lock_hashes) = &chain_spec.eoa_lock_hashes {
+            log::info!(
+                "Load chain spec from {}, rollup_type_hash: 0x{}, chain_id: {}",
+                source,
+                chain_spec.rollup_type_hash,
+                chain_spec.chain_id
+            );
+            return Ok(Chain {
+                eoa_locks: eoa_locks(eoa_lock_hashes, &config.web3_eoa_types),
+                chain_spec: chain_spec.clone(),
+                source: *source,
+                verified: false,
+            });
+        }
+    }
+
+    let godwoken_async_client = GodwokenAsyncClient::with_url(&config.godwoken_rpc_url)?;
+    let poll_interval = Duration::from_secs(config.interval.poll_secs);
+    let expected = chain_spec
+        .as_ref()
+        .map(|(source, chain_spec)| (*source, chain_spec));
+    let chain_spec = retry_until_connected("gw_get_node_info", poll_interval, || {
+        verify_chain(pool, &godwoken_async_client, expected)
+    })
+    .await?;
+    let eoa_locks = eoa_locks(
+        chain_spec
+            .eoa_lock_hashes
+            .as_ref()
+            .ok_or_else(|| anyhow!("godwoken node info has no eoa scripts"))?,
+        &config.web3_eoa_types,
+    );
+    Ok(Chain {
+        chain_spec,
+        eoa_locks,
+        source: expected.map_or("godwoken", |(source, _)| source),
+        verified: true,
+    })
+}
+
+// Check the expected chain spec and `chain_metadata` against godwoken, and record the chain
+// metadata and EOA locks missing in the database. Returns the chain spec with the EOA locks of
+// the node. Connection errors are returned as `RpcClientError::ConnectionError`.
+pub async fn verify_chain(
+    pool: &PgPool,
+    godwoken_async_client: &GodwokenAsyncClient,
+    expected: Option<(&str, &ChainSpec)>,
+) -> Result<ChainSpec> {
+    let node_info = godwoken_async_client.get_node_info().await?;
+    let genesis_block_hash = godwoken_async_client
+        .get_block_hash(0)
+        .await?
+        .ok_or_else(|| anyhow!("genesis block not exist on godwoken!"))?;
+
+    let node_chain_spec = ChainSpec::from_node_info(&node_info)?;
+    let mut chain_spec = match expected {
+        Some((source, chain_spec)) => {
+            ensure_same_chain((source, chain_spec), ("godwoken", &node_chain_spec))?;
+            chain_spec.clone()
+        }
+        None => node_chain_spec.clone(),
+    };
+    // the configuration file may not list the EOA locks
+    chain_spec.eoa_lock_hashes = node_chain_spec.eoa_lock_hashes.clone();
+
+    let node_metadata = ChainMetadata {
+        chain_spec,
+        genesis_block_hash,
+    };
+    let db_metadata = match load_chain_metadata(pool).await? {
+        Some(metadata) if metadata.chain_spec.eoa_lock_hashes.is_some() => metadata,
+        Some(metadata) => {
+            // recorded before chain_eoa_locks existed, take the EOA locks of the same chain's node
+            ensure_same_metadata(&metadata, &node_metadata)?;
+            insert_chain_metadata(pool, &node_metadata).await?;
+            log::info!(
+                "Record EOA locks of the chain: {}",
+                node_metadata
+                    .chain_spec
+                    .eoa_lock_hashes
+                    .iter()
+                    .flatten()
+                    .map(|(eoa_type, _)| eoa_type.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            load_chain_metadata(pool).await?.unwrap_or(metadata)
+        }
+        None => {
+            // databases indexed before chain_metadata existed must hold the same genesis block
+            let db_genesis_block_hash: Option<(Vec<u8>,)> =
+                sqlx::query_as("SELECT hash FROM blocks WHERE number = 0")
+                    .fetch_optional(pool)
+                    .await?;
+            if let Some((hash,)) = db_genesis_block_hash {
+                let hash = H256::from_slice(&hash)?;
+                if hash != node_metadata.genesis_block_hash {
+                    return Err(anyhow!(
+                        "genesis block hash 0x{} in database doesn't match godwoken 0x{}, the database belongs to another chain",
+                        hash,
+                        node_metadata.genesis_block_hash
+                    ));
+                }
+            }
+            insert_chain_metadata(pool, &node_metadata).await?;
+            log::info!(
+                "Record chain metadata, rollup_type_hash: 0x{}, chain_id: {}",
+                node_metadata.chain_spec.rollup_type_hash,
+                node_metadata.chain_spec.chain_id
+            );
+            load_chain_metadata(pool)
+                .await?
+                .ok_or_else(|| anyhow!("chain metadata not exist after insert!"))?
+        }
+    };
+
+    ensure_same_metadata(&db_metadata, &node_metadata)?;
+    Ok(node_metadata.chain_spec)
+}
+
+fn ensure_same_metadata(db_metadata: &ChainMetadata, node_metadata: &ChainMetadata) -> Result<()> {
+    let diffs = db_metadata.diff(node_metadata);
+    if diffs.is_empty() {
+        return Ok(());
+    }
+    let diffs = diffs
+        .into_iter()
+        .map(|(field, db_value, node_value)| {
+            format!("{}: database {}, godwoken {}", field, db_value, node_value)
+        })
+        .collect::<Vec<_>>();
+    Err(anyhow!(
+        "godwoken node belongs to another chain than the database, {}",
+        diffs.join("; ")
+    ))
+}
+
+fn ensure_same_chain(a: (&str, &ChainSpec), b: (&str, &ChainSpec)) -> Result<()> {
+    let ((a_source, a_chain_spec), (b_source, b_chain_spec)) = (a, b);
+    let diffs = a_chain_spec.diff(b_chain_spec);
+    if diffs.is_empty() {
+        return Ok(());
+    }
+    let diffs = diffs
+        .into_iter()
+        .map(|(field, a_value, b_value)| {
+            format!(
+                "{}: {} {}, {} {}",
+                field, a_source, a_value, b_source, b_value
+            )
+        })
+        .collect::<Vec<_>>();
+    Err(anyhow!(
+        "chain spec of {} doesn't match {}, {}",
+        a_source,
+        b_source,
+        diffs.join("; ")
+    ))
+}
+
+// Call `request` until godwoken is connected, sleeping `interval` after the first connection failure
+// and doubling up to MAX_BACKOFF. Other errors are returned.
+async fn retry_until_connected<T, F, Fut>(what: &str, interval: Duration, request: F) -> Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut backoff = interval;
+    loop {
+        match request().await {
+            Ok(value) => return Ok(value),
+            Err(err) if is_connection_error(&err) => {
+                log::warn!("Request {} failed, retry in {:?}: {}", what, backoff, err);
+                smol::Timer::after(backoff).await;
+                backoff = std::cmp::min(backoff * 2, MAX_BACKOFF);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+pub fn is_connection_error(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<RpcClientError>(),
+        Some(RpcClientError::ConnectionError(_, _))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{helper::test_database, migration::run_migrations};
+
+    fn chain_metadata() -> ChainMetadata {
+        let eoa_lock_hashes = [("eth", H256([4u8; 32])), ("tron", H256([5u8; 32]))]
+            .into_iter()
+            .map(|(eoa_type, type_hash)| (eoa_type.to_string(), type_hash))
+            .collect();
+        ChainMetadata {
+            chain_spec: ChainSpec {
+                rollup_type_hash: H256([1u8; 32]),
+                chain_id: 71402,
+                l2_sudt_type_script_hash: H256([2u8; 32]),
+                polyjuice_type_script_hash: H256([3u8; 32]),
+                eth_account_lock_hash: H256([4u8; 32]),
+                eoa_lock_hashes: Some(eoa_lock_hashes),
+            },
+            genesis_block_hash: H256([6u8; 32]),
+        }
+    }
+
+    #[test]
+    fn test_chain_metadata_diff() {
+        let db_metadata = chain_metadata();
+        assert!(db_metadata.diff(&chain_metadata()).is_empty());
+
+        let mut node_metadata = chain_metadata();
+        node_metadata.chain_spec.chain_id = 71401;
+        node_metadata.genesis_block_hash = H256([7u8; 32]);
+        let diffs = db_metadata.diff(&node_metadata);
+        assert_eq!(
+            diffs,
+            vec![
+                ("chain_id", "71402".to_string(), "71401".to_string()),
+                (
+                    "genesis_block_hash",
+                    format!("0x{}", "06".repeat(32)),
+                    format!("0x{}", "07".repeat(32))
+                ),
+            ]
+        );
+
+        // a lock added to or replaced on the node is a mismatch
+        let mut node_metadata = chain_metadata();
+        let node_eoa_locks = node_metadata.chain_spec.eoa_lock_hashes.as_mut().unwrap();
+        node_eoa_locks.insert("tron".to_string(), H256([8u8; 32]));
+        node_eoa_locks.insert("bitcoin".to_string(), H256([9u8; 32]));
+        let diffs = db_metadata.diff(&node_metadata);
+        assert_eq!(
+            diffs,
+            vec![(
+                "eoa_lock_hashes",
+                format!(
+                    "{{eth: 0x{}, tron: 0x{}}}",
+                    "04".repeat(32),
+                    "05".repeat(32)
+                ),
+                format!(
+                    "{{bitcoin: 0x{}, eth: 0x{}, tron: 0x{}}}",
+                    "09".repeat(32),
+                    "04".repeat(32),
+                    "08".repeat(32)
+                ),
+            )]
+        );
+
+        // EOA locks are not compared when one side doesn't list them
+        let mut legacy_metadata = chain_metadata();
+        legacy_metadata.chain_spec.eoa_lock_hashes = None;
+        assert!(legacy_metadata.diff(&node_metadata).is_empty());
+        assert!(node_metadata.diff(&legacy_metadata).is_empty());
+    }
+
+    #[test]
+    fn test_mismatch_error() {
+        let db_metadata = chain_metadata();
+        ensure_same_metadata(&db_metadata, &chain_metadata()).unwrap();
+
+        let mut node_metadata = chain_metadata();
+        node_metadata.chain_spec.rollup_type_hash = H256([0u8; 32]);
+        node_metadata.chain_spec.chain_id = 1;
+        let err = ensure_same_metadata(&db_metadata, &node_metadata).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "godwoken node belongs to another chain than the database, rollup_type_hash: database 0x{}, godwoken 0x{}; chain_id: database 71402, godwoken 1",
+                "01".repeat(32),
+                "00".repeat(32)
+            )
+        );
+
+        let err = ensure_same_chain(
+            ("configuration file", &db_metadata.chain_spec),
+            ("godwoken", &node_metadata.chain_spec),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "chain spec of configuration file doesn't match godwoken, rollup_type_hash: configuration file 0x{}, godwoken 0x{}; chain_id: configuration file 71402, godwoken 1",
+                "01".repeat(32),
+                "00".repeat(32)
+            )
+        );
+    }
+
+    #[test]
+    fn test_record_chain_metadata() {
+        smol::block_on(async {
+            let pool = match test_database("test_record_chain_metadata").await {
+                Some(pool) => pool,
+                None => return,
+            };
+            run_migrations(&pool).await.unwrap();
+            assert_eq!(load_chain_metadata(&pool).await.unwrap(), None);
+
+            let metadata = chain_metadata();
+            insert_chain_metadata(&pool, &metadata).await.unwrap();
+            assert_eq!(
+                load_chain_metadata(&pool).await.unwrap(),
+                Some(metadata.clone())
+            );
+
+            // metadata recorded before chain_eoa_locks existed
+            sqlx::query("DELETE FROM chain_eoa_locks")
+                .execute(&pool)
+                .await
+                .unwrap();
+            let legacy_metadata = load_chain_metadata(&pool).await.unwrap().unwrap();
+            assert_eq!(legacy_metadata.chain_spec.eoa_lock_hashes, None);
+
+            // inserting again only fills the EOA locks, the recorded row is kept
+            let mut node_metadata = metadata.clone();
+            node_metadata.chain_spec.chain_id = 1;
+            insert_chain_metadata(&pool, &node_metadata).await.unwrap();
+            assert_eq!(load_chain_metadata(&pool).await.unwrap(), Some(metadata));
+        });
+    }
+
+    #[test]
+    fn test_load_chain_offline() {
+        smol::block_on(async {
+            let pool = match test_database("test_load_chain_offline").await {
+                Some(pool) => pool,
+                None => return,
+            };
+            run_migrations(&pool).await.unwrap();
+            // nothing listens on it, godwoken must not be requested
+            let mut config = IndexerConfig {
+                godwoken_rpc_url: "http://127.0.0.1:1".to_string(),
+                web3_eoa_types: vec!["eth".to_string()],
+                chain_spec: Some(chain_metadata().chain_spec),
+                ..Default::default()
+            };
+
+            let chain = load_chain(&pool, &config).await.unwrap();
+            assert_eq!(chain.source, "configuration file");
+            assert!(!chain.verified);
+            assert_eq!(chain.chain_spec, chain_metadata().chain_spec);
+            let eoa_locks = chain
+                .eoa_locks
+                .iter()
+                .map(|lock| (lock.eoa_type.as_str(), lock.web3_visible))
+                .collect::<Vec<_>>();
+            assert_eq!(eoa_locks, vec![("eth", true), ("tron", false)]);
+
+            // the database lists the EOA locks missing in the configuration file
+            insert_chain_metadata(&pool, &chain_metadata())
+                .await
+                .unwrap();
+            let chain_spec = config.chain_spec.as_mut().unwrap();
+            chain_spec.eoa_lock_hashes = None;
+            let chain = load_chain(&pool, &config).await.unwrap();
+            assert_eq!(chain.source, "configuration file");
+            assert_eq!(chain.chain_spec, chain_metadata().chain_spec);
+
+            config.chain_spec = None;
+            let chain = load_chain(&pool, &config).await.unwrap();
+            assert_eq!(chain.source, "database");
+            assert!(!chain.verified);
+            assert_eq!(chain.chain_spec, chain_metadata().chain_spec);
+
+            // a configuration file of another chain is refused without godwoken
+            let mut chain_spec = chain_metadata().chain_spec;
+            chain_spec.chain_id = 1;
+            config.chain_spec = Some(chain_spec);
+            let err = load_chain(&pool, &config).await.unwrap_err();
+            assert!(err
+                .to_string()
+                .starts_with("chain spec of configuration file doesn't match database"));
+        });
+    }
+}
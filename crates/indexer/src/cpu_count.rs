@@ -1,7 +1,4 @@
-lazy_static::lazy_static! {
-  pub static ref CPU_COUNT: Option<usize> = {
-      std::env::args()
-      .nth(4)
-      .map(|num| num.parse::<usize>().unwrap())
-  };
-}
+use once_cell::sync::OnceCell;
+
+// Number of cpu cores used by update to convert rows, set by the `--threads` option
+pub static CPU_COUNT: OnceCell<usize> = OnceCell::new();
@@ -0,0 +1,450 @@
+use ethabi::{long_signature, ParamType, Token, Uint};
+
+use crate::types::{ErcApproval, ErcTransfer, Log, TokenStandard};
+
+lazy_static::lazy_static! {
+    // Transfer(address,address,uint256), shared by ERC-20 and ERC-721
+    static ref TRANSFER_TOPIC: ethabi::Hash = long_signature(
+        "Transfer",
+        &[ParamType::Address, ParamType::Address, ParamType::Uint(256)]
+    );
+    // Approval(address,address,uint256), shared by ERC-20 and ERC-721
+    static ref APPROVAL_TOPIC: ethabi::Hash = long_signature(
+        "Approval",
+        &[ParamType::Address, ParamType::Address, ParamType::Uint(256)]
+    );
+    static ref TRANSFER_SINGLE_TOPIC: ethabi::Hash = long_signature(
+        "TransferSingle",
+        &[
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Uint(256)
+        ]
+    );
+    static ref TRANSFER_BATCH_TOPIC: ethabi::Hash = long_signature(
+        "TransferBatch",
+        &[
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Array(Box::new(ParamType::Uint(256))),
+            ParamType::Array(Box::new(ParamType::Uint(256)))
+        ]
+    );
+}
+
+// Decoded standard token events of a log, logs which match a known topic but
+// not the event layout are emitted by non-standard contracts and ignored.
+#[derive(Debug, Default)]
+pub struct ErcEvents {
+    pub transfers: Vec<ErcTransfer>,
+    pub approvals: Vec<ErcApproval>,
+}
+
+impl ErcEvents {
+    pub fn extend(&mut self, other: ErcEvents) {
+        self.transfers.extend(other.transfers);
+        self.approvals.extend(other.approvals);
+    }
+}
+
+pub fn decode_erc_events(log: &Log) -> ErcEvents {
+    let mut events = ErcEvents::default();
+    let topic0 = match log.topics.first() {
+        Some(topic) => topic.as_slice(),
+        None => return events,
+    };
+
+    if topic0 == TRANSFER_TOPIC.as_bytes() {
+        // ERC-20: 2 indexed addresses, value in data
+        // ERC-721: 2 indexed addresses and indexed token id
+        let (standard, token_id, amount) = match (log.topics.len(), log.data.len()) {
+            (3, 32) => (TokenStandard::Erc20, None, Uint::from_big_endian(&log.data)),
+            (4, 0) => (
+                TokenStandard::Erc721,
+                Some(topic_to_uint(log, 3)),
+                Uint::one(),
+            ),
+            _ => return events,
+        };
+        events.transfers.push(ErcTransfer::new(
+            log,
+            0,
+            standard,
+            None,
+            topic_to_address(log, 1),
+            topic_to_address(log, 2),
+            token_id,
+            amount,
+        ));
+    } else if topic0 == APPROVAL_TOPIC.as_bytes() {
+        let (standard, token_id, amount) = match (log.topics.len(), log.data.len()) {
+            (3, 32) => (
+                TokenStandard::Erc20,
+                None,
+                Some(Uint::from_big_endian(&log.data)),
+            ),
+            (4, 0) => (TokenStandard::Erc721, Some(topic_to_uint(log, 3)), None),
+            _ => return events,
+        };
+        events.approvals.push(ErcApproval {
+            transaction_hash: log.transaction_hash,
+            transaction_index: log.transaction_index,
+            block_number: log.block_number,
+            block_hash: log.block_hash,
+            log_index: log.log_index,
+            standard,
+            token_contract: log.address,
+            owner: topic_to_address(log, 1),
+            spender: topic_to_address(log, 2),
+            token_id,
+            amount,
+        });
+    } else if topic0 == TRANSFER_SINGLE_TOPIC.as_bytes() {
+        if log.topics.len() != 4 {
+            return events;
+        }
+        let tokens = match ethabi::decode(&[ParamType::Uint(256), ParamType::Uint(256)], &log.data)
+        {
+            Ok(tokens) => tokens,
+            Err(_) => return events,
+        };
+        if let [Token::Uint(token_id), Token::Uint(amount)] = tokens.as_slice() {
+            events.transfers.push(ErcTransfer::new(
+                log,
+                0,
+                TokenStandard::Erc1155,
+                Some(topic_to_address(log, 1)),
+                topic_to_address(log, 2),
+                topic_to_address(log, 3),
+                Some(*token_id),
+                *amount,
+            ));
+        }
+    } else if topic0 == TRANSFER_BATCH_TOPIC.as_bytes() {
+        if log.topics.len() != 4 {
+            return events;
+        }
+        let uint_array = ParamType::Array(Box::new(ParamType::Uint(256)));
+        let tokens = match ethabi::decode(&[uint_array.clone(), uint_array], &log.data) {
+            Ok(tokens) => tokens,
+            Err(_) => return events,
+        };
+        if let [Token::Array(token_ids), Token::Array(amounts)] = tokens.as_slice() {
+            if token_ids.len() != amounts.len() {
+                return events;
+            }
+            for (batch_index, (token_id, amount)) in token_ids.iter().zip(amounts).enumerate() {
+                if let (Token::Uint(token_id), Token::Uint(amount)) = (token_id, amount) {
+                    events.transfers.push(ErcTransfer::new(
+                        log,
+                        batch_index as u32,
+                        TokenStandard::Erc1155,
+                        Some(topic_to_address(log, 1)),
+                        topic_to_address(log, 2),
+                        topic_to_address(log, 3),
+                        Some(*token_id),
+                        *amount,
+                    ));
+                }
+            }
+        }
+    }
+
+    events
+}
+
+// Indexed address is left padded to 32 bytes
+fn topic_to_address(log: &Log, index: usize) -> [u8; 20] {
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&log.topics[index].as_slice()[12..32]);
+    address
+}
+
+fn topic_to_uint(log: &Log, index: usize) -> Uint {
+    Uint::from_big_endian(log.topics[index].as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use gw_common::H256;
+
+    use super::*;
+    use crate::helper::from_hex;
+
+    const TRANSFER: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+    const APPROVAL: &str = "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925";
+    const TRANSFER_SINGLE: &str =
+        "0xc3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62";
+    const TRANSFER_BATCH: &str =
+        "0x4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb";
+
+    fn h256(value: &str) -> H256 {
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&from_hex(value));
+        hash.into()
+    }
+
+    fn address(value: &str) -> [u8; 20] {
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&from_hex(value));
+        address
+    }
+
+    fn topic(address: &str) -> String {
+        format!(
+            "0x000000000000000000000000{}",
+            address.trim_start_matches("0x")
+        )
+    }
+
+    fn log(contract: &str, topics: &[&str], data: &[u8], log_index: u32) -> Log {
+        Log::new(
+            h256("0xf34ad2dec9ef8d155c014f1ef135b56023bc13b85fe20909d3506a02ad908156"),
+            4,
+            12_000_000,
+            h256("0x3c9c46a46b17361cd1ac3ed3401c9a268095c1810bf991c470c139f8441e1d0b"),
+            address(contract),
+            data.to_vec(),
+            log_index,
+            topics.iter().map(|t| h256(t)).collect(),
+        )
+    }
+
+    #[test]
+    fn test_event_topics() {
+        assert_eq!(TRANSFER_TOPIC.as_bytes(), from_hex(TRANSFER));
+        assert_eq!(APPROVAL_TOPIC.as_bytes(), from_hex(APPROVAL));
+        assert_eq!(TRANSFER_SINGLE_TOPIC.as_bytes(), from_hex(TRANSFER_SINGLE));
+        assert_eq!(TRANSFER_BATCH_TOPIC.as_bytes(), from_hex(TRANSFER_BATCH));
+    }
+
+    // LINK transfer in ethereum mainnet block 12000000, log 2
+    #[test]
+    fn test_decode_erc20_transfer() {
+        let log = log(
+            "0x514910771AF9Ca656af840dff83E8264EcF986CA",
+            &[
+                TRANSFER,
+                "0x000000000000000000000000d7efcbb86efdd9e8de014dafa5944aae36e817e4",
+                "0x000000000000000000000000ead301653827fd6d2e2c2f16374791b4fb61cc2e",
+            ],
+            &from_hex("0x000000000000000000000000000000000000000000000000471fa858b9e08000"),
+            2,
+        );
+        let events = decode_erc_events(&log);
+
+        assert!(events.approvals.is_empty());
+        assert_eq!(events.transfers.len(), 1);
+        let transfer = &events.transfers[0];
+        assert_eq!(transfer.standard, TokenStandard::Erc20);
+        assert_eq!(
+            transfer.token_contract,
+            address("0x514910771AF9Ca656af840dff83E8264EcF986CA")
+        );
+        assert_eq!(transfer.operator, None);
+        assert_eq!(
+            transfer.from_address,
+            address("0xd7efcbb86efdd9e8de014dafa5944aae36e817e4")
+        );
+        assert_eq!(
+            transfer.to_address,
+            address("0xead301653827fd6d2e2c2f16374791b4fb61cc2e")
+        );
+        assert_eq!(transfer.token_id, None);
+        assert_eq!(transfer.amount, Uint::from(0x471fa858b9e08000u64));
+        assert_eq!(transfer.log_index, 2);
+        assert_eq!(transfer.batch_index, 0);
+    }
+
+    // Mint of token 0x42 in ethereum mainnet block 12000000, log 158
+    #[test]
+    fn test_decode_erc721_transfer() {
+        let log = log(
+            "0x892555E75350E11f2058d086C72b9C94C9493d72",
+            &[
+                TRANSFER,
+                "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "0x000000000000000000000000b278ada59e7af95a0c3de7699d8946b853f1e38a",
+                "0x0000000000000000000000000000000000000000000000000000000000000042",
+            ],
+            &[],
+            158,
+        );
+        let events = decode_erc_events(&log);
+
+        assert_eq!(events.transfers.len(), 1);
+        let transfer = &events.transfers[0];
+        assert_eq!(transfer.standard, TokenStandard::Erc721);
+        assert_eq!(transfer.operator, None);
+        assert_eq!(transfer.from_address, [0u8; 20]);
+        assert_eq!(
+            transfer.to_address,
+            address("0xb278ada59e7af95a0c3de7699d8946b853f1e38a")
+        );
+        assert_eq!(transfer.token_id, Some(Uint::from(0x42u64)));
+        assert_eq!(transfer.amount, Uint::one());
+    }
+
+    // ERC-20 and ERC-721 share the Transfer topic, only the number of topics and the data
+    // length tell them apart
+    #[test]
+    fn test_decode_transfer_topic_count() {
+        let from = topic("d7efcbb86efdd9e8de014dafa5944aae36e817e4");
+        let to = topic("ead301653827fd6d2e2c2f16374791b4fb61cc2e");
+        let value = from_hex("0x0000000000000000000000000000000000000000000000000000000000000042");
+        let contract = "0x514910771AF9Ca656af840dff83E8264EcF986CA";
+
+        let erc20 = decode_erc_events(&log(contract, &[TRANSFER, &from, &to], &value, 0));
+        assert_eq!(erc20.transfers[0].standard, TokenStandard::Erc20);
+        assert_eq!(erc20.transfers[0].token_id, None);
+        assert_eq!(erc20.transfers[0].amount, Uint::from(0x42u64));
+
+        let value_topic = format!("0x{}", faster_hex::hex_string(&value).unwrap());
+        let erc721 = decode_erc_events(&log(
+            contract,
+            &[TRANSFER, &from, &to, &value_topic],
+            &[],
+            0,
+        ));
+        assert_eq!(erc721.transfers[0].standard, TokenStandard::Erc721);
+        assert_eq!(erc721.transfers[0].token_id, Some(Uint::from(0x42u64)));
+        assert_eq!(erc721.transfers[0].amount, Uint::one());
+
+        // neither layout, emitted by non-standard contracts
+        let (from, to, value_topic) = (from.as_str(), to.as_str(), value_topic.as_str());
+        for (topics, data) in [
+            (vec![TRANSFER, from, to], vec![]),
+            (vec![TRANSFER, from, to, value_topic], value.clone()),
+            (vec![TRANSFER, from], value.clone()),
+            (
+                vec![TRANSFER, from, to],
+                [value.clone(), value.clone()].concat(),
+            ),
+        ] {
+            let events = decode_erc_events(&log(contract, &topics, &data, 0));
+            assert!(events.transfers.is_empty());
+            assert!(events.approvals.is_empty());
+        }
+    }
+
+    // Unlimited approval in ethereum mainnet block 12000000, log 13
+    #[test]
+    fn test_decode_erc20_approval() {
+        let log = log(
+            "0xBBc2AE13b23d715c30720F079fcd9B4a74093505",
+            &[
+                APPROVAL,
+                "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                "0x00000000000000000000000095e6f48254609a6ee006f7d493c8e5fb97094cef",
+            ],
+            &[0xff; 32],
+            13,
+        );
+        let events = decode_erc_events(&log);
+
+        assert!(events.transfers.is_empty());
+        assert_eq!(events.approvals.len(), 1);
+        let approval = &events.approvals[0];
+        assert_eq!(approval.standard, TokenStandard::Erc20);
+        assert_eq!(
+            approval.owner,
+            address("0x0000000000000000000000007f150bd6f54c40a34d7c3d5e9f56"[24..].as_ref())
+        );
+        assert_eq!(
+            approval.spender,
+            address("0x95e6f48254609a6ee006f7d493c8e5fb97094cef")
+        );
+        assert_eq!(approval.token_id, None);
+        assert_eq!(approval.amount, Some(Uint::max_value()));
+    }
+
+    #[test]
+    fn test_decode_erc1155_transfer_single() {
+        let operator = topic("7a250d5630b4cf539739df2c5dacb4c659f2488d");
+        let from = topic("d7efcbb86efdd9e8de014dafa5944aae36e817e4");
+        let to = topic("ead301653827fd6d2e2c2f16374791b4fb61cc2e");
+        let data = ethabi::encode(&[
+            Token::Uint(Uint::from(7u64)),
+            Token::Uint(Uint::from(100u64)),
+        ]);
+        let contract = "0x495f947276749ce646f68ac8c248420045cb7b5e";
+        let events = decode_erc_events(&log(
+            contract,
+            &[TRANSFER_SINGLE, &operator, &from, &to],
+            &data,
+            5,
+        ));
+
+        assert_eq!(events.transfers.len(), 1);
+        let transfer = &events.transfers[0];
+        assert_eq!(transfer.standard, TokenStandard::Erc1155);
+        assert_eq!(
+            transfer.operator,
+            Some(address("0x7a250d5630b4cf539739df2c5dacb4c659f2488d"))
+        );
+        assert_eq!(
+            transfer.from_address,
+            address("0xd7efcbb86efdd9e8de014dafa5944aae36e817e4")
+        );
+        assert_eq!(
+            transfer.to_address,
+            address("0xead301653827fd6d2e2c2f16374791b4fb61cc2e")
+        );
+        assert_eq!(transfer.token_id, Some(Uint::from(7u64)));
+        assert_eq!(transfer.amount, Uint::from(100u64));
+        assert_eq!(transfer.batch_index, 0);
+
+        // the operator topic is missing
+        let events = decode_erc_events(&log(contract, &[TRANSFER_SINGLE, &from, &to], &data, 5));
+        assert!(events.transfers.is_empty());
+    }
+
+    #[test]
+    fn test_decode_erc1155_transfer_batch() {
+        let operator = topic("7a250d5630b4cf539739df2c5dacb4c659f2488d");
+        let from = topic("d7efcbb86efdd9e8de014dafa5944aae36e817e4");
+        let to = topic("ead301653827fd6d2e2c2f16374791b4fb61cc2e");
+        let uints = |values: &[u64]| {
+            Token::Array(values.iter().map(|v| Token::Uint(Uint::from(*v))).collect())
+        };
+        let data = ethabi::encode(&[uints(&[1, 2, 3]), uints(&[10, 20, 30])]);
+        let contract = "0x495f947276749ce646f68ac8c248420045cb7b5e";
+        let events = decode_erc_events(&log(
+            contract,
+            &[TRANSFER_BATCH, &operator, &from, &to],
+            &data,
+            6,
+        ));
+
+        assert_eq!(events.transfers.len(), 3);
+        for (i, transfer) in events.transfers.iter().enumerate() {
+            assert_eq!(transfer.standard, TokenStandard::Erc1155);
+            assert_eq!(transfer.log_index, 6);
+            assert_eq!(transfer.batch_index, i as u32);
+            assert_eq!(transfer.token_id, Some(Uint::from(i as u64 + 1)));
+            assert_eq!(transfer.amount, Uint::from((i as u64 + 1) * 10));
+        }
+
+        // ids and values of different lengths
+        let data = ethabi::encode(&[uints(&[1, 2]), uints(&[10])]);
+        let events = decode_erc_events(&log(
+            contract,
+            &[TRANSFER_BATCH, &operator, &from, &to],
+            &data,
+            6,
+        ));
+        assert!(events.transfers.is_empty());
+
+        // data which isn't abi encoded
+        let events = decode_erc_events(&log(
+            contract,
+            &[TRANSFER_BATCH, &operator, &from, &to],
+            &[1, 2, 3],
+            6,
+        ));
+        assert!(events.transfers.is_empty());
+    }
+}
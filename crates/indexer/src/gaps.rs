@@ -0,0 +1,316 @@
+use anyhow::Result;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use sqlx::PgPool;
+
+// Block numbers which should be (re)indexed, found in `blocks` by `scan_gaps`
+#[derive(Debug, Default)]
+pub struct Gaps {
+    // [start, end] ranges of block numbers missing below the local tip
+    pub missing: Vec<(u64, u64)>,
+    // blocks whose parent_hash doesn't equal the hash of the previous block
+    pub discontinuities: Vec<u64>,
+}
+
+impl Gaps {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.discontinuities.is_empty()
+    }
+
+    pub fn missing_len(&self) -> u64 {
+        self.missing
+            .iter()
+            .map(|(start, end)| end - start + 1)
+            .sum()
+    }
+
+    // Take at most `limit` blocks to backfill, in block number order. Blocks above `tip` are
+    // dropped, they are indexed by the sync. The two blocks around a discontinuity are taken
+    // together, so `limit` may be exceeded by one when it is smaller than 2.
+    pub fn take(&mut self, limit: usize, tip: Option<u64>) -> Vec<Backfill> {
+        let mut blocks = vec![];
+        let tip = match tip {
+            Some(tip) => tip,
+            None => {
+                *self = Gaps::default();
+                return blocks;
+            }
+        };
+        while blocks.len() < limit {
+            if let Some((start, end)) = self.missing.first_mut() {
+                // ranges are sorted, all the remaining ones are above the tip
+                if *start > tip {
+                    self.missing.clear();
+                    continue;
+                }
+                blocks.push(Backfill::Missing(*start));
+                if *start >= (*end).min(tip) {
+                    self.missing.remove(0);
+                } else {
+                    *start += 1;
+                }
+            } else if let Some(&number) = self.discontinuities.first() {
+                if number > tip {
+                    self.discontinuities.clear();
+                    continue;
+                }
+                if !blocks.is_empty() && blocks.len() + 2 > limit {
+                    break;
+                }
+                self.discontinuities.remove(0);
+                blocks.extend((number.saturating_sub(1)..=number).map(Backfill::Discontinuity));
+            } else {
+                break;
+            }
+        }
+        blocks
+    }
+}
+
+// A block to backfill, taken from `Gaps`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backfill {
+    // missing block, inserted
+    Missing(u64),
+    // block around a parent hash discontinuity, replaced if it doesn't match godwoken
+    Discontinuity(u64),
+}
+
+// Scan the blocks numbered from `from` up to the local tip, `from` is 0 or the last block of the
+// previous scan so that a scan only reads the blocks indexed since then. Returns the gaps and
+// the last scanned block, None if there are no blocks from `from`.
+pub async fn scan_gaps(pool: &PgPool, from: u64) -> Result<(Gaps, Option<u64>)> {
+    let (first, last): (Option<Decimal>, Option<Decimal>) =
+        sqlx::query_as("SELECT min(number), max(number) FROM blocks WHERE number >= $1")
+            .bind(Decimal::from(from))
+            .fetch_one(pool)
+            .await?;
+    let (first, last) = match (
+        first.and_then(|n| n.to_u64()),
+        last.and_then(|n| n.to_u64()),
+    ) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Ok((Gaps::default(), None)),
+    };
+
+    let mut missing = vec![];
+    if first > from {
+        missing.push((from, first - 1));
+    }
+
+    // blocks above `last` are being inserted by the sync, they are left to the next scan
+    let rows: Vec<(Decimal, Decimal)> = sqlx::query_as(
+        "SELECT number + 1, next_number - 1 FROM (
+            SELECT number, lead(number) OVER (ORDER BY number) AS next_number FROM blocks
+            WHERE number >= $1 AND number <= $2
+        ) AS t WHERE next_number > number + 1 ORDER BY number",
+    )
+    .bind(Decimal::from(from))
+    .bind(Decimal::from(last))
+    .fetch_all(pool)
+    .await?;
+    for (start, end) in rows {
+        if let (Some(start), Some(end)) = (start.to_u64(), end.to_u64()) {
+            missing.push((start, end));
+        }
+    }
+
+    let rows: Vec<(Decimal,)> = sqlx::query_as(
+        "SELECT b.number FROM blocks b JOIN blocks p ON p.number = b.number - 1
+        WHERE b.number > $1 AND b.number <= $2 AND b.parent_hash <> p.hash ORDER BY b.number",
+    )
+    .bind(Decimal::from(from))
+    .bind(Decimal::from(last))
+    .fetch_all(pool)
+    .await?;
+    let discontinuities = rows.into_iter().filter_map(|(n,)| n.to_u64()).collect();
+
+    Ok((
+        Gaps {
+            missing,
+            discontinuities,
+        },
+        Some(last),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{helper::test_database, migration::run_migrations};
+
+    fn hash(number: u64) -> Vec<u8> {
+        vec![number as u8 + 1; 32]
+    }
+
+    // Insert blocks whose parent hash is the hash of the previous block number, except the
+    // ones in `forks`
+    async fn insert_blocks(pool: &PgPool, numbers: &[u64], forks: &[u64]) {
+        for &number in numbers {
+            let parent_hash = if forks.contains(&number) {
+                vec![0xffu8; 32]
+            } else {
+                hash(number.saturating_sub(1))
+            };
+            sqlx::query(
+                "INSERT INTO blocks (number, hash, parent_hash, gas_limit, gas_used, miner, size, timestamp)
+                VALUES ($1, $2, $3, 0, 0, $4, 0, now())",
+            )
+            .bind(Decimal::from(number))
+            .bind(hash(number))
+            .bind(parent_hash)
+            .bind(vec![0u8; 20])
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+    }
+
+    async fn scan(name: &str, numbers: &[u64], forks: &[u64]) -> Option<Gaps> {
+        let pool = test_database(name).await?;
+        run_migrations(&pool).await.unwrap();
+        insert_blocks(&pool, numbers, forks).await;
+        let (gaps, last) = scan_gaps(&pool, 0).await.unwrap();
+        assert_eq!(last, numbers.iter().copied().max());
+        Some(gaps)
+    }
+
+    #[test]
+    fn test_scan_gaps() {
+        smol::block_on(async {
+            let gaps = match scan("test_scan_gaps_empty", &[], &[]).await {
+                Some(gaps) => gaps,
+                None => return,
+            };
+            assert!(gaps.is_empty());
+
+            let gaps = scan("test_scan_gaps_none", &[0, 1, 2, 3], &[])
+                .await
+                .unwrap();
+            assert!(gaps.is_empty());
+
+            // gap at 0
+            let gaps = scan("test_scan_gaps_at_zero", &[3, 4, 5], &[])
+                .await
+                .unwrap();
+            assert_eq!(gaps.missing, vec![(0, 2)]);
+            assert_eq!(gaps.missing_len(), 3);
+
+            // gap right below the tip
+            let gaps = scan("test_scan_gaps_at_tip", &[0, 1, 2, 3, 4, 5, 8], &[])
+                .await
+                .unwrap();
+            assert_eq!(gaps.missing, vec![(6, 7)]);
+
+            // multiple gaps, including one at 0
+            let gaps = scan("test_scan_gaps_multiple", &[2, 5, 6, 9, 11], &[])
+                .await
+                .unwrap();
+            assert_eq!(gaps.missing, vec![(0, 1), (3, 4), (7, 8), (10, 10)]);
+            assert_eq!(gaps.missing_len(), 7);
+            assert!(gaps.discontinuities.is_empty());
+
+            // a block after a gap has no previous block to compare with
+            let gaps = scan("test_scan_gaps_discontinuities", &[0, 1, 2, 3, 5], &[2, 5])
+                .await
+                .unwrap();
+            assert_eq!(gaps.missing, vec![(4, 4)]);
+            assert_eq!(gaps.discontinuities, vec![2]);
+        });
+    }
+
+    #[test]
+    fn test_scan_gaps_from() {
+        smol::block_on(async {
+            let pool = match test_database("test_scan_gaps_from").await {
+                Some(pool) => pool,
+                None => return,
+            };
+            run_migrations(&pool).await.unwrap();
+            insert_blocks(&pool, &[0, 1, 3, 4, 5, 8], &[4]).await;
+
+            // gaps below `from` are skipped, the block at `from` is compared with the next one
+            let (gaps, last) = scan_gaps(&pool, 4).await.unwrap();
+            assert_eq!(gaps.missing, vec![(6, 7)]);
+            assert!(gaps.discontinuities.is_empty());
+            assert_eq!(last, Some(8));
+
+            let (gaps, _) = scan_gaps(&pool, 3).await.unwrap();
+            assert_eq!(gaps.missing, vec![(6, 7)]);
+            assert_eq!(gaps.discontinuities, vec![4]);
+
+            // blocks inserted since the last scan
+            insert_blocks(&pool, &[10, 11], &[]).await;
+            let (gaps, last) = scan_gaps(&pool, 8).await.unwrap();
+            assert_eq!(gaps.missing, vec![(9, 9)]);
+            assert!(gaps.discontinuities.is_empty());
+            assert_eq!(last, Some(11));
+
+            // the block at `from` was rolled back
+            let (gaps, last) = scan_gaps(&pool, 9).await.unwrap();
+            assert_eq!(gaps.missing, vec![(9, 9)]);
+            assert!(gaps.discontinuities.is_empty());
+            assert_eq!(last, Some(11));
+
+            let (gaps, last) = scan_gaps(&pool, 12).await.unwrap();
+            assert!(gaps.is_empty());
+            assert_eq!(last, None);
+        });
+    }
+
+    #[test]
+    fn test_take_gaps() {
+        let mut gaps = Gaps {
+            missing: vec![(0, 1), (3, 5)],
+            discontinuities: vec![8, 12],
+        };
+        assert_eq!(
+            gaps.take(3, Some(20)),
+            vec![
+                Backfill::Missing(0),
+                Backfill::Missing(1),
+                Backfill::Missing(3)
+            ]
+        );
+        assert_eq!(gaps.missing, vec![(4, 5)]);
+        // a discontinuity isn't split
+        assert_eq!(
+            gaps.take(3, Some(20)),
+            vec![Backfill::Missing(4), Backfill::Missing(5)]
+        );
+        assert_eq!(
+            gaps.take(3, Some(20)),
+            vec![Backfill::Discontinuity(7), Backfill::Discontinuity(8)]
+        );
+        assert_eq!(
+            gaps.take(1, Some(20)),
+            vec![Backfill::Discontinuity(11), Backfill::Discontinuity(12)]
+        );
+        assert!(gaps.is_empty());
+        assert!(gaps.take(3, Some(20)).is_empty());
+    }
+
+    #[test]
+    fn test_take_gaps_above_tip() {
+        // blocks above the tip were rolled back
+        let mut gaps = Gaps {
+            missing: vec![(2, 6), (9, 10)],
+            discontinuities: vec![12],
+        };
+        assert_eq!(
+            gaps.take(10, Some(4)),
+            vec![
+                Backfill::Missing(2),
+                Backfill::Missing(3),
+                Backfill::Missing(4)
+            ]
+        );
+        assert!(gaps.is_empty());
+
+        let mut gaps = Gaps {
+            missing: vec![(2, 6)],
+            discontinuities: vec![3],
+        };
+        assert!(gaps.take(10, None).is_empty());
+        assert!(gaps.is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+use anyhow::Result;
+use ethabi::{ParamType, Token};
+use rust_decimal::Decimal;
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::{
+    insert_l2_block::uint_to_big_decimal,
+    types::{GaslessTransaction, Transaction, UserOperation},
+};
+
+// First 4 bytes of keccak hash of handleOp((address,bytes,uint256,uint256,uint256,uint256,bytes))
+const ENTRYPOINT_HANDLE_OP_SELECTOR: [u8; 4] = [0xfb, 0x43, 0x50, 0xd8];
+
+// Gasless payload = ENTRYPOINT_HANDLE_OP_SELECTOR + abiEncode(UserOperation),
+// returns None if the input is not a gasless payload.
+pub fn decode_gasless_payload(input: &[u8]) -> Option<UserOperation> {
+    if input.len() < 4 || input[0..4] != ENTRYPOINT_HANDLE_OP_SELECTOR {
+        return None;
+    }
+    let user_operation_type = ParamType::Tuple(vec![
+        ParamType::Address,
+        ParamType::Bytes,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Bytes,
+    ]);
+    let tokens = ethabi::decode(&[user_operation_type], &input[4..]).ok()?;
+    let fields = match tokens.as_slice() {
+        [Token::Tuple(fields)] => fields,
+        _ => return None,
+    };
+    match fields.as_slice() {
+        [Token::Address(call_contract), Token::Bytes(call_data), Token::Uint(call_gas_limit), Token::Uint(verification_gas_limit), Token::Uint(max_fee_per_gas), Token::Uint(max_priority_fee_per_gas), Token::Bytes(paymaster_and_data)] => {
+            Some(UserOperation {
+                call_contract: call_contract.0,
+                call_data: call_data.clone(),
+                call_gas_limit: *call_gas_limit,
+                verification_gas_limit: *verification_gas_limit,
+                max_fee_per_gas: *max_fee_per_gas,
+                max_priority_fee_per_gas: *max_priority_fee_per_gas,
+                paymaster_and_data: paymaster_and_data.clone(),
+            })
+        }
+        _ => None,
+    }
+}
+
+// Same rules as `isGaslessTransaction` in api-server: zero gas price, calling the entrypoint
+// and the input can be decoded.
+pub fn parse_gasless_transaction(
+    tx: &Transaction,
+    entrypoint: &[u8; 20],
+) -> Option<GaslessTransaction> {
+    if tx.gas_price != 0 || tx.to_address.as_ref() != Some(entrypoint) {
+        return None;
+    }
+    let user_operation = decode_gasless_payload(&tx.data)?;
+    Some(GaslessTransaction {
+        transaction_hash: tx.gw_tx_hash,
+        block_number: tx.block_number,
+        block_hash: tx.block_hash,
+        transaction_index: tx.transaction_index,
+        sender: tx.from_address,
+        entrypoint: *entrypoint,
+        user_operation,
+    })
+}
+
+pub async fn insert_gasless_transactions(
+    gasless_txs: Vec<GaslessTransaction>,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<usize> {
+    if gasless_txs.is_empty() {
+        return Ok(0);
+    }
+    let gasless_txs_len = gasless_txs.len();
+
+    let mut values = Vec::with_capacity(gasless_txs_len);
+    for gasless_tx in gasless_txs {
+        let op = &gasless_tx.user_operation;
+        let gas_fields = (
+            uint_to_big_decimal(&op.call_gas_limit)?,
+            uint_to_big_decimal(&op.verification_gas_limit)?,
+            uint_to_big_decimal(&op.max_fee_per_gas)?,
+            uint_to_big_decimal(&op.max_priority_fee_per_gas)?,
+        );
+        values.push((gasless_tx, gas_fields));
+    }
+
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+        "INSERT INTO gasless_transactions (transaction_hash, block_number, block_hash, transaction_index, sender, entrypoint, call_contract, call_data, call_gas_limit, verification_gas_limit, max_fee_per_gas, max_priority_fee_per_gas, paymaster, paymaster_and_data) ",
+    );
+    query_builder.push_values(
+        values,
+        |mut b,
+         (
+            gasless_tx,
+            (call_gas_limit, verification_gas_limit, max_fee_per_gas, max_priority_fee_per_gas),
+        )| {
+            let op = gasless_tx.user_operation;
+            b.push_bind(gasless_tx.transaction_hash.as_slice().to_vec())
+                .push_bind(Decimal::from(gasless_tx.block_number))
+                .push_bind(gasless_tx.block_hash.as_slice().to_vec())
+                .push_bind(i64::from(gasless_tx.transaction_index))
+                .push_bind(gasless_tx.sender.to_vec())
+                .push_bind(gasless_tx.entrypoint.to_vec())
+                .push_bind(op.call_contract.to_vec())
+                .push_bind(op.call_data.clone())
+                .push_bind(call_gas_limit)
+                .push_bind(verification_gas_limit)
+                .push_bind(max_fee_per_gas)
+                .push_bind(max_priority_fee_per_gas)
+                .push_bind(op.paymaster().map(|paymaster| paymaster.to_vec()))
+                .push_bind(op.paymaster_and_data);
+        },
+    );
+    query_builder.build().execute(pg_tx).await?;
+
+    Ok(gasless_txs_len)
+}
+
+pub async fn delete_gasless_transactions(
+    block_number: u64,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<()> {
+    sqlx::query("DELETE FROM gasless_transactions WHERE block_number = $1")
+        .bind(Decimal::from(block_number))
+        .execute(pg_tx)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use gw_common::H256;
+    use gw_types::U256;
+
+    use super::*;
+    use crate::helper::from_hex;
+
+    // Payload encoded with ethers by `encodeGaslessPayload` of api-server
+    const PAYLOAD: &str = "0xfb4350d800000000000000000000000000000000000000000000000000000000000000200000000000000000000000001df923e4f009663b0fddc1775dac783b85f432fb00000000000000000000000000000000000000000000000000000000000000e000000000000000000000000000000000000000000000000000000000000061a800000000000000000000000000000000000000000000000000000000000061a800000000000000000000000000000000000000000000000000000000000061a800000000000000000000000000000000000000000000000000000000000061a800000000000000000000000000000000000000000000000000000000000001200000000000000000000000000000000000000000000000000000000000000002ffff00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000141df923e4f009663b0fddc1775dac783b85f432fb000000000000000000000000";
+    const CALL_CONTRACT: &str = "0x1df923e4f009663b0fddc1775dac783b85f432fb";
+    const ENTRYPOINT: &str = "0x9a11f47c0729fc56d9c44c059987d40703249569";
+
+    fn address(value: &str) -> [u8; 20] {
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&from_hex(value));
+        address
+    }
+
+    fn tx(to_address: [u8; 20], gas_price: u128, data: Vec<u8>) -> Transaction {
+        Transaction::new(
+            H256::zero(),
+            Some(71402),
+            10,
+            H256::zero(),
+            2,
+            [0x11u8; 20],
+            Some(to_address),
+            U256::zero(),
+            0,
+            1_000_000,
+            gas_price,
+            data,
+            [0u8; 32],
+            [0u8; 32],
+            0,
+            0,
+            0,
+            None,
+            0,
+            Some(0),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_decode_gasless_payload() {
+        let user_operation = decode_gasless_payload(&from_hex(PAYLOAD)).unwrap();
+        assert_eq!(user_operation.call_contract, address(CALL_CONTRACT));
+        assert_eq!(user_operation.call_data, vec![0xffu8, 0xff]);
+        assert_eq!(user_operation.call_gas_limit, ethabi::Uint::from(0x61a8u64));
+        assert_eq!(
+            user_operation.verification_gas_limit,
+            ethabi::Uint::from(0x61a8u64)
+        );
+        assert_eq!(
+            user_operation.max_fee_per_gas,
+            ethabi::Uint::from(0x61a8u64)
+        );
+        assert_eq!(
+            user_operation.max_priority_fee_per_gas,
+            ethabi::Uint::from(0x61a8u64)
+        );
+        assert_eq!(user_operation.paymaster_and_data, from_hex(CALL_CONTRACT));
+        assert_eq!(user_operation.paymaster(), Some(address(CALL_CONTRACT)));
+    }
+
+    #[test]
+    fn test_parse_gasless_transaction() {
+        let entrypoint = address(ENTRYPOINT);
+        let gasless =
+            parse_gasless_transaction(&tx(entrypoint, 0, from_hex(PAYLOAD)), &entrypoint).unwrap();
+        assert_eq!(gasless.sender, [0x11u8; 20]);
+        assert_eq!(gasless.entrypoint, entrypoint);
+        assert_eq!(gasless.block_number, 10);
+        assert_eq!(gasless.transaction_index, 2);
+        assert_eq!(gasless.user_operation.call_contract, address(CALL_CONTRACT));
+
+        // not zero gas price
+        assert!(
+            parse_gasless_transaction(&tx(entrypoint, 1, from_hex(PAYLOAD)), &entrypoint).is_none()
+        );
+        // not calling the entrypoint
+        assert!(parse_gasless_transaction(
+            &tx(address(CALL_CONTRACT), 0, from_hex(PAYLOAD)),
+            &entrypoint
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_decode_malformed_gasless_payload() {
+        let payload = from_hex(PAYLOAD);
+        assert!(decode_gasless_payload(&[]).is_none());
+        assert!(decode_gasless_payload(&payload[..4]).is_none());
+        // truncated in the head of the UserOperation
+        assert!(decode_gasless_payload(&payload[..100]).is_none());
+
+        // other selector
+        let mut other_selector = payload.clone();
+        other_selector[0] = 0;
+        assert!(decode_gasless_payload(&other_selector).is_none());
+
+        // offset of the UserOperation points out of the payload
+        let mut bad_offset = payload.clone();
+        bad_offset[4..36].copy_from_slice(&[0xffu8; 32]);
+        assert!(decode_gasless_payload(&bad_offset).is_none());
+
+        // length of call_data overflows the payload
+        let mut bad_length = payload.clone();
+        let call_data_length = 4 + 32 + 0xe0;
+        bad_length[call_data_length..call_data_length + 32].copy_from_slice(&[0xffu8; 32]);
+        assert!(decode_gasless_payload(&bad_length).is_none());
+
+        // truncated anywhere must not panic
+        for len in 0..payload.len() {
+            decode_gasless_payload(&payload[..len]);
+        }
+    }
+}
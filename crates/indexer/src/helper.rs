@@ -292,3 +292,41 @@ pub fn parse_log(item: &LogItem, tx_hash: &H256) -> Result<GwLog> {
 pub fn hex(raw: &[u8]) -> Result<String> {
     Ok(format!("0x{}", faster_hex::hex_string(raw)?))
 }
+
+// Decode a hex string with or without the 0x prefix, used by test fixtures
+#[cfg(test)]
+pub fn from_hex(value: &str) -> Vec<u8> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    let mut raw = vec![0u8; value.len() / 2];
+    faster_hex::hex_decode(value.as_bytes(), &mut raw).expect("invalid hex string");
+    raw
+}
+
+// Create the empty database `name` on the server of `TEST_DATABASE_URL`, replacing an existing
+// one. Tests using it are skipped when `TEST_DATABASE_URL` is not set.
+#[cfg(test)]
+pub async fn test_database(name: &str) -> Option<sqlx::PgPool> {
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+    let url = std::env::var("TEST_DATABASE_URL").ok()?;
+    let server = sqlx::PgPool::connect(&url)
+        .await
+        .expect("connect TEST_DATABASE_URL");
+    sqlx::query(&format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", name))
+        .execute(&server)
+        .await
+        .expect("drop test database");
+    sqlx::query(&format!("CREATE DATABASE {}", name))
+        .execute(&server)
+        .await
+        .expect("create test database");
+    server.close().await;
+
+    let opts: PgConnectOptions = url.parse().expect("parse TEST_DATABASE_URL");
+    let pool = PgPoolOptions::new()
+        .max_connections(2)
+        .connect_with(opts.database(name))
+        .await
+        .expect("connect test database");
+    Some(pool)
+}
@@ -4,14 +4,30 @@ use std::{
 };
 
 use crate::{
+    accounts::{
+        delete_accounts_from, insert_accounts, query_account_scripts, Account, AccountCache,
+        AccountType,
+    },
+    anomaly::{check_signatures, delete_anomalies, insert_anomalies},
+    config::{BatchConfig, EoaAddressLayout, EoaLockConfig, RetryConfig},
+    erc_events::{decode_erc_events, ErcEvents},
+    gasless::{
+        delete_gasless_transactions, insert_gasless_transactions, parse_gasless_transaction,
+    },
     helper::{hex, parse_log, GwLog, PolyjuiceArgs, GW_LOG_POLYJUICE_SYSTEM},
     insert_l2_block::{
-        insert_web3_block, insert_web3_txs_and_logs, update_web3_block, update_web3_txs_and_logs,
+        delete_erc_events, delete_gw_transactions, delete_token_transfers, delete_web3_block,
+        delete_withdrawals, insert_erc_events, insert_gw_transactions, insert_token_transfers,
+        insert_web3_block, insert_web3_txs_and_logs, insert_withdrawals, update_web3_block,
+        update_web3_txs_and_logs,
     },
-    pool::POOL,
+    pool::{POOL, POOL_FOR_UPDATE},
+    signature::resolve_tx_type,
     types::{
-        Block as Web3Block, Log as Web3Log, Transaction as Web3Transaction,
-        TransactionWithLogs as Web3TransactionWithLogs,
+        logs_bloom, merge_bloom, Block as Web3Block,
+        BlockWithTransactions as Web3BlockWithTransactions, Bloom, GwTransaction, Log as Web3Log,
+        TokenTransfer, TokenTransferKind, Transaction as Web3Transaction,
+        TransactionWithLogs as Web3TransactionWithLogs, Withdrawal,
     },
 };
 use anyhow::{anyhow, Result};
@@ -20,29 +36,49 @@ use ckb_types::H256;
 use gw_common::{builtins::CKB_SUDT_ACCOUNT_ID, registry_address::RegistryAddress};
 use gw_types::{
     bytes::Bytes,
-    packed::{L2Block, L2Transaction, SUDTArgs, SUDTArgsUnion, Script, TxReceipt},
+    packed::{L2Block, L2Transaction, LogItemVec, SUDTArgs, SUDTArgsUnion, Script, TxReceipt},
     prelude::Unpack as GwUnpack,
     prelude::*,
     U256,
 };
-use gw_web3_rpc_client::{
-    convertion, godwoken_async_client::GodwokenAsyncClient, godwoken_rpc_client::GodwokenRpcClient,
-};
+use gw_web3_rpc_client::{convertion, godwoken_async_client::GodwokenAsyncClient};
 use itertools::Itertools;
 use rayon::prelude::*;
 use rust_decimal::{prelude::ToPrimitive, Decimal};
 use sqlx::types::chrono::{DateTime, NaiveDateTime, Utc};
 
 const MILLIS_PER_SEC: u64 = 1_000;
-const TX_BATCH_SIZE: usize = 100;
+
+// Receipt of a transaction, with the return data of the transaction if godwoken returns it
+#[derive(Clone)]
+struct Receipt {
+    tx_receipt: TxReceipt,
+    return_data: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WriteMode {
+    Insert,
+    // update rows of a block already in the database
+    Update,
+    // delete all rows of the block then insert it, for blocks with missing or unexpected rows
+    Replace,
+}
 
 pub struct Web3Indexer {
     l2_sudt_type_script_hash: H256,
     polyjuice_type_script_hash: H256,
     rollup_type_hash: H256,
-    allowed_eoa_hashes: HashSet<H256>,
-    godwoken_rpc_client: GodwokenRpcClient,
+    eth_account_lock_hash: H256,
+    // EOA locks of the rollup, by lock type hash
+    eoa_locks: HashMap<H256, EoaLockConfig>,
     godwoken_async_client: GodwokenAsyncClient,
+    account_cache: AccountCache,
+    persist_accounts: bool,
+    verify_signatures: bool,
+    gasless_entrypoint_address: Option<[u8; 20]>,
+    batch: BatchConfig,
+    retry: RetryConfig,
 }
 
 impl Web3Indexer {
@@ -51,21 +87,51 @@ impl Web3Indexer {
         polyjuice_type_script_hash: H256,
         rollup_type_hash: H256,
         eth_account_lock_hash: H256,
+        eoa_locks: Vec<EoaLockConfig>,
         gw_rpc_url: &str,
-    ) -> Self {
-        let mut allowed_eoa_hashes = HashSet::default();
-        allowed_eoa_hashes.insert(eth_account_lock_hash);
-        let godwoken_rpc_client = GodwokenRpcClient::new(gw_rpc_url);
-        let godwoken_async_client = GodwokenAsyncClient::with_url(gw_rpc_url).unwrap(); // TODO:
+        account_cache_size: usize,
+        persist_accounts: bool,
+        verify_signatures: bool,
+        gasless_entrypoint_address: Option<[u8; 20]>,
+        batch: BatchConfig,
+        retry: RetryConfig,
+    ) -> Result<Self> {
+        let eoa_locks = eoa_locks
+            .into_iter()
+            .map(|lock| (lock.type_hash.clone(), lock))
+            .collect();
+        let godwoken_async_client = GodwokenAsyncClient::with_url(gw_rpc_url)?;
 
-        Web3Indexer {
+        Ok(Web3Indexer {
             l2_sudt_type_script_hash,
             polyjuice_type_script_hash,
             rollup_type_hash,
-            allowed_eoa_hashes,
-            godwoken_rpc_client,
+            eth_account_lock_hash,
+            eoa_locks,
             godwoken_async_client,
-        }
+            account_cache: AccountCache::new(account_cache_size),
+            persist_accounts,
+            verify_signatures,
+            gasless_entrypoint_address,
+            batch,
+            retry,
+        })
+    }
+
+    // Returns (hits, misses) of the account script cache
+    pub fn account_cache_stats(&self) -> (u64, u64) {
+        self.account_cache.stats()
+    }
+
+    // Forget accounts seen in blocks >= `start_block_number`, called when these blocks are rolled back.
+    // Returns the number of deleted accounts.
+    pub async fn rollback_accounts(
+        &self,
+        start_block_number: u64,
+        pg_tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<u64> {
+        self.account_cache.clear();
+        delete_accounts_from(start_block_number, pg_tx).await
     }
 
     pub async fn update_l2_block(&self, l2_block: L2Block) -> Result<(usize, usize)> {
@@ -82,13 +148,56 @@ impl Web3Indexer {
     }
 
     pub async fn store_l2_block(&self, l2_block: L2Block) -> Result<(usize, usize)> {
-        let number: u64 = l2_block.raw().number().unpack();
+        let receipts = self.fetch_transaction_receipts(&l2_block).await?;
+        let web3_block_with_txs = self.convert_l2_block(&l2_block, &receipts).await?;
+        self.store_web3_block(web3_block_with_txs).await
+    }
+
+    /// Fetch block `number` and the receipts of its transactions from godwoken, and convert
+    /// them into web3 rows without touching the database.
+    ///
+    /// Returns `None` if the block hasn't been produced yet.
+    pub async fn prefetch_l2_block(
+        &self,
+        number: u64,
+    ) -> Result<Option<Web3BlockWithTransactions>> {
+        let l2_block = match self
+            .godwoken_async_client
+            .get_block_by_number(number)
+            .await?
+        {
+            Some(block_view) => convertion::to_l2_block(block_view),
+            None => return Ok(None),
+        };
+        let receipts = self.fetch_transaction_receipts(&l2_block).await?;
+        let web3_block_with_txs = self.convert_l2_block(&l2_block, &receipts).await?;
+        Ok(Some(web3_block_with_txs))
+    }
+
+    /// Replace all rows of a block returned by `prefetch_l2_block` in one database transaction,
+    /// the block is inserted if it isn't in the database.
+    pub async fn replace_web3_block(
+        &self,
+        web3_block_with_txs: Web3BlockWithTransactions,
+    ) -> Result<(usize, usize)> {
+        self.write_web3_block(web3_block_with_txs, WriteMode::Replace)
+            .await
+    }
+
+    /// Write a block returned by `prefetch_l2_block`, skip it if it's already in the database.
+    pub async fn store_web3_block(
+        &self,
+        web3_block_with_txs: Web3BlockWithTransactions,
+    ) -> Result<(usize, usize)> {
+        let number = web3_block_with_txs.block.number;
         let local_tip_number = self.tip_number().await?.unwrap_or(0);
         let mut txs_len = 0;
         let mut logs_len = 0;
         if number > local_tip_number || self.query_number(number).await?.is_none() {
             // insert l2 block
-            (txs_len, logs_len) = self.insert_or_update_l2block(l2_block, false).await?;
+            (txs_len, logs_len) = self
+                .write_web3_block(web3_block_with_txs, WriteMode::Insert)
+                .await?;
             log::debug!(
                 "web3 indexer: sync new block #{}, {} txs, {} logs",
                 number,
@@ -99,6 +208,29 @@ impl Web3Indexer {
         Ok((txs_len, logs_len))
     }
 
+    // Check signatures of transactions in block `number` and replace its anomalies,
+    // returns the number of anomalies, None if the block is not found.
+    pub async fn audit_block(&self, number: u64) -> Result<Option<usize>> {
+        let mut block_with_txs = match self.prefetch_l2_block(number).await? {
+            Some(block_with_txs) => block_with_txs,
+            None => return Ok(None),
+        };
+        // envelope types are only resolved by the conversion when signatures are verified
+        if !self.verify_signatures {
+            for tx in block_with_txs.txs.iter_mut() {
+                resolve_tx_type(&mut tx.tx);
+            }
+        }
+        let anomalies = check_signatures(&block_with_txs.txs);
+
+        let mut pg_tx = POOL.begin().await?;
+        delete_anomalies(number, &mut pg_tx).await?;
+        let anomalies_len = insert_anomalies(anomalies, &mut pg_tx).await?;
+        pg_tx.commit().await?;
+
+        Ok(Some(anomalies_len))
+    }
+
     async fn query_number(&self, number: u64) -> Result<Option<u64>> {
         let row: Option<(Decimal,)> = sqlx::query_as(&format!(
             "SELECT number FROM blocks WHERE number={} LIMIT 1",
@@ -109,7 +241,7 @@ impl Web3Indexer {
         Ok(row.and_then(|(n,)| n.to_u64()))
     }
 
-    async fn tip_number(&self) -> Result<Option<u64>> {
+    pub async fn tip_number(&self) -> Result<Option<u64>> {
         let row: Option<(Decimal,)> =
             sqlx::query_as("SELECT number FROM blocks ORDER BY number DESC LIMIT 1")
                 .fetch_optional(&*POOL)
@@ -120,10 +252,11 @@ impl Web3Indexer {
     // NOTE: remember to update `tx_index`, `cumulative_gas_used`, `log.transaction_index`
     fn filter_single_transaction(
         &self,
-        l2_transaction: L2Transaction,
+        l2_transaction: &L2Transaction,
         block_number: u64,
         block_hash: gw_common::H256,
         id_script_map: &std::collections::HashMap<u32, Option<Script>>,
+        receipts: &HashMap<gw_common::H256, Receipt>,
     ) -> Result<Option<Web3TransactionWithLogs>> {
         let gw_tx_hash: gw_common::H256 = l2_transaction.hash().into();
         let from_id: u32 = l2_transaction.raw().from_id().unpack();
@@ -137,24 +270,22 @@ impl Web3Indexer {
             .ok_or_else(|| anyhow!("Can't get script by id: {:?}", from_id))?;
 
         let from_script_code_hash: H256 = from_script.code_hash().unpack();
-        // skip tx not in the allowed eoa account lock
-        if !self.allowed_eoa_hashes.contains(&from_script_code_hash) {
-            // continue;
-            return Ok(None);
-        }
-        // from_address is the script's args in eth account lock
-        let from_script_args = from_script.args().raw_data();
-        if from_script_args.len() != 52 && from_script_args[0..32] == self.rollup_type_hash.0 {
-            return Err(anyhow!(
-                "Wrong from_address's script args, from_script_args: {:?}",
-                from_script_args
-            ));
-        }
-        let from_address = {
-            let mut buf = [0u8; 20];
-            buf.copy_from_slice(&from_script_args[32..52]);
-            buf
+        // skip tx not from a web3 visible eoa lock, it is recorded in gw_transactions
+        let from_lock = match self.eoa_locks.get(&from_script_code_hash) {
+            Some(lock) if lock.web3_visible => lock,
+            _ => return Ok(None),
         };
+        // from_address is in the script's args of the eoa lock
+        let from_script_args = from_script.args().raw_data();
+        let from_address = from_lock
+            .address_layout
+            .and_then(|layout| layout.extract_address(&from_script_args, &self.rollup_type_hash))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Wrong from_address's script args, from_script_args: {:?}",
+                    from_script_args
+                )
+            })?;
 
         // extract to_id corresponding script, check code_hash is either polyjuice contract code_hash or sudt contract code_hash
         let to_id = l2_transaction.raw().to_id().unpack();
@@ -219,7 +350,10 @@ impl Web3Indexer {
             let input = polyjuice_args.input.clone().unwrap_or_default();
 
             // read logs
-            let tx_receipt: TxReceipt = self.get_transaction_receipt(gw_tx_hash, block_number)?;
+            let Receipt {
+                tx_receipt,
+                return_data,
+            } = find_receipt(receipts, &gw_tx_hash, block_number)?;
             let log_item_vec = tx_receipt.logs();
 
             // read polyjuice system log
@@ -228,14 +362,14 @@ impl Web3Indexer {
                 .into_iter()
                 .find(|item| u8::from(item.service_flag()) == GW_LOG_POLYJUICE_SYSTEM);
 
-            let (contract_address, tx_gas_used) = match polyjuice_system_log_item {
+            let (contract_address, tx_gas_used, status_code) = match polyjuice_system_log_item {
                 Some(item) => {
                     let polyjuice_system_log = parse_log(&item, &gw_tx_hash)?;
                     if let GwLog::PolyjuiceSystem {
                         gas_used,
                         cumulative_gas_used: _,
                         created_address,
-                        status_code: _,
+                        status_code,
                     } = polyjuice_system_log
                     {
                         let tx_gas_used: u128 = gas_used.into();
@@ -246,7 +380,7 @@ impl Web3Indexer {
                             } else {
                                 None
                             };
-                        (contract_address, tx_gas_used)
+                        (contract_address, tx_gas_used, Some(status_code as i32))
                     } else {
                         return Err(anyhow!(
                             "can't find polyjuice system log from logs: tx_hash: {}",
@@ -264,12 +398,15 @@ impl Web3Indexer {
                         block_number,
                         tx_receipt.exit_code()
                     );
-                    (None, polyjuice_args.gas_limit as u128)
+                    (None, polyjuice_args.gas_limit as u128, None)
                 }
             };
 
+            // only the return data of failed transactions is kept
+            let revert_data = return_data
+                .filter(|data| !data.is_empty() && status_code.map_or(false, |code| code != 0));
             let exit_code: u8 = tx_receipt.exit_code().into();
-            let web3_transaction = Web3Transaction::new(
+            let mut web3_transaction = Web3Transaction::new(
                 gw_tx_hash,
                 Some(chain_id),
                 block_number,
@@ -290,13 +427,20 @@ impl Web3Indexer {
                 tx_gas_used,
                 contract_address,
                 exit_code,
+                status_code,
+                revert_data,
             );
+            // the signed envelope type is only known from the signature, which is only recovered
+            // when signatures are verified, otherwise the transaction is indexed as legacy
+            if self.verify_signatures {
+                resolve_tx_type(&mut web3_transaction);
+            }
 
             let web3_logs = {
                 let mut logs: Vec<Web3Log> = vec![];
                 // log_index is a log's index in block, not transaction, should update later.
                 let mut log_index = 0;
-                for log_item in log_item_vec {
+                for log_item in log_item_vec.clone() {
                     let log = parse_log(&log_item, &gw_tx_hash)?;
                     match log {
                         GwLog::PolyjuiceSystem { .. } => {
@@ -320,17 +464,26 @@ impl Web3Indexer {
                             logs.push(web3_log);
                             log_index += 1;
                         }
-                        // TODO: Given the fact that Ethereum doesn't emit event for native ether transfer at system level, the SudtTransfer/SudtPayFee logs in polyjuice provide more info than we need here and could be ignored so far.
+                        // Ethereum doesn't emit event for native ether transfer at system level,
+                        // SudtTransfer/SudtPayFee logs are indexed into token_transfers instead.
                         GwLog::SudtTransfer { .. } => {}
                         GwLog::SudtPayFee { .. } => {}
                     }
                 }
                 logs
             };
+            let token_transfers =
+                parse_token_transfers(log_item_vec, gw_tx_hash, block_number, block_hash)?;
+            let gasless = self
+                .gasless_entrypoint_address
+                .as_ref()
+                .and_then(|entrypoint| parse_gasless_transaction(&web3_transaction, entrypoint));
 
             let web3_tx_with_logs = Web3TransactionWithLogs {
                 tx: web3_transaction,
                 logs: web3_logs,
+                token_transfers,
+                gasless,
             };
             // tx_index += 1;
             return Ok(Some(web3_tx_with_logs));
@@ -369,8 +522,7 @@ impl Web3Indexer {
 
                     let nonce: u32 = l2_transaction.raw().nonce().unpack();
 
-                    let tx_receipt: TxReceipt =
-                        self.get_transaction_receipt(gw_tx_hash, block_number)?;
+                    let tx_receipt = find_receipt(receipts, &gw_tx_hash, block_number)?.tx_receipt;
 
                     let exit_code: u8 = tx_receipt.exit_code().into();
                     let web3_transaction = Web3Transaction::new(
@@ -393,11 +545,22 @@ impl Web3Indexer {
                         gas_limit,
                         None,
                         exit_code,
+                        None,
+                        None,
                     );
 
+                    let token_transfers = parse_token_transfers(
+                        tx_receipt.logs(),
+                        gw_tx_hash,
+                        block_number,
+                        block_hash,
+                    )?;
+
                     let web3_tx_with_logs = Web3TransactionWithLogs {
                         tx: web3_transaction,
                         logs: vec![],
+                        token_transfers,
+                        gasless: None,
                     };
 
                     return Ok(Some(web3_tx_with_logs));
@@ -409,10 +572,96 @@ impl Web3Indexer {
         Ok(None)
     }
 
+    // Convert txs of a block, txs skipped by `filter_single_transaction` are kept as godwoken
+    // native transactions.
+    fn convert_transactions(
+        &self,
+        l2_transactions: &[L2Transaction],
+        block_number: u64,
+        block_hash: gw_common::H256,
+        id_script_map: &std::collections::HashMap<u32, Option<Script>>,
+        receipts: &HashMap<gw_common::H256, Receipt>,
+    ) -> Result<(Vec<Web3TransactionWithLogs>, Vec<GwTransaction>)> {
+        let web3_txs = l2_transactions
+            .par_iter()
+            .map(|tx| {
+                self.filter_single_transaction(
+                    tx,
+                    block_number,
+                    block_hash,
+                    id_script_map,
+                    receipts,
+                )
+            })
+            .collect::<Result<Vec<Option<Web3TransactionWithLogs>>>>()?;
+
+        let gw_transactions = l2_transactions
+            .iter()
+            .zip(web3_txs.iter())
+            .enumerate()
+            .filter(|(_, (_, web3_tx))| web3_tx.is_none())
+            .map(|(index, (tx, _))| {
+                self.convert_gw_transaction(
+                    tx,
+                    index as u32,
+                    block_number,
+                    block_hash,
+                    id_script_map,
+                    receipts,
+                )
+            })
+            .collect::<Result<Vec<GwTransaction>>>()?;
+
+        Ok((web3_txs.into_iter().flatten().collect(), gw_transactions))
+    }
+
+    fn convert_gw_transaction(
+        &self,
+        l2_transaction: &L2Transaction,
+        l2_tx_index: u32,
+        block_number: u64,
+        block_hash: gw_common::H256,
+        id_script_map: &std::collections::HashMap<u32, Option<Script>>,
+        receipts: &HashMap<gw_common::H256, Receipt>,
+    ) -> Result<GwTransaction> {
+        let from_id: u32 = l2_transaction.raw().from_id().unpack();
+        let from_script = id_script_map
+            .get(&from_id)
+            .ok_or_else(|| anyhow!("Can't get script by id in hashmap: {:?}", from_id))?
+            .as_ref()
+            .ok_or_else(|| anyhow!("Can't get script by id: {:?}", from_id))?;
+        let from_script_code_hash: H256 = from_script.code_hash().unpack();
+        // None for unknown eoa locks
+        let eoa_type = self
+            .eoa_locks
+            .get(&from_script_code_hash)
+            .map(|lock| lock.eoa_type.clone());
+
+        let gw_tx_hash: gw_common::H256 = l2_transaction.hash().into();
+        let tx_receipt = find_receipt(receipts, &gw_tx_hash, block_number)?.tx_receipt;
+        let args: Bytes = l2_transaction.raw().args().unpack();
+        Ok(GwTransaction {
+            hash: gw_tx_hash,
+            block_number,
+            block_hash,
+            l2_tx_index,
+            from_id,
+            to_id: l2_transaction.raw().to_id().unpack(),
+            nonce: l2_transaction.raw().nonce().unpack(),
+            args: args.to_vec(),
+            // script hash of the sender account
+            from_lock_hash: blake2b_256(from_script.as_slice()).into(),
+            eoa_type,
+            exit_code: tx_receipt.exit_code().into(),
+        })
+    }
+
+    // Returns the scripts of the from / to accounts, and the accounts requested from godwoken,
+    // i.e. first seen by this process
     async fn batch_from_script(
         &self,
         txs: &[L2Transaction],
-    ) -> Result<std::collections::HashMap<u32, Option<Script>>> {
+    ) -> Result<(HashMap<u32, Option<Script>>, Vec<Account>)> {
         let from_ids = txs
             .iter()
             .map(|tx| {
@@ -445,17 +694,163 @@ impl Web3Indexer {
             ids_len
         );
 
-        let scripts = batch_account_id_to_script(&self.godwoken_async_client, ids.clone()).await?;
-
         let mut hashmap = HashMap::<u32, Option<Script>>::new();
-        scripts
+
+        // only unseen ids reach the godwoken rpc
+        let mut missed_ids = vec![];
+        for id in ids {
+            match self.account_cache.get(id) {
+                Some(script) => {
+                    hashmap.insert(id, Some(script));
+                }
+                None => missed_ids.push(id),
+            }
+        }
+        if self.persist_accounts {
+            for (id, script) in query_account_scripts(&missed_ids).await? {
+                self.account_cache.put(id, script.clone());
+                hashmap.insert(id, Some(script));
+            }
+            missed_ids.retain(|id| !hashmap.contains_key(id));
+        }
+        self.account_cache
+            .record((ids_len - missed_ids.len()) as u64, missed_ids.len() as u64);
+        let (hits, misses) = self.account_cache.stats();
+        log::debug!(
+            "account cache, missed ids len: {}, total hits: {}, total misses: {}",
+            missed_ids.len(),
+            hits,
+            misses
+        );
+
+        let accounts =
+            batch_account_id_to_script(&self.godwoken_async_client, missed_ids.clone()).await?;
+
+        let mut new_accounts = vec![];
+        for (account, id) in accounts.into_iter().zip(missed_ids.into_iter()) {
+            match account {
+                Some((script_hash, script)) => {
+                    self.account_cache.put(id, script.clone());
+                    new_accounts.push(self.build_account(id, script_hash, &script));
+                    hashmap.insert(id, Some(script));
+                }
+                None => {
+                    hashmap.insert(id, None);
+                }
+            }
+        }
+
+        Ok((hashmap, new_accounts))
+    }
+
+    // Eth address of an eth EOA script, None for other scripts
+    fn eoa_eth_address(&self, script: &Script) -> Option<[u8; 20]> {
+        let code_hash: H256 = script.code_hash().unpack();
+        if code_hash != self.eth_account_lock_hash {
+            return None;
+        }
+        let args = script.args().raw_data();
+        EoaAddressLayout::RollupTypeHashPrefixed.extract_address(&args, &self.rollup_type_hash)
+    }
+
+    async fn convert_withdrawals(
+        &self,
+        l2_block: &L2Block,
+        block_hash: gw_common::H256,
+    ) -> Result<Vec<Withdrawal>> {
+        let block_number: u64 = l2_block.raw().number().unpack();
+        let raw_requests = l2_block
+            .withdrawals()
             .into_iter()
-            .zip(ids.into_iter())
-            .for_each(|(value, id)| {
-                hashmap.insert(id, value);
-            });
+            .map(|request| request.raw())
+            .collect::<Vec<_>>();
+        if raw_requests.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // withdrawal requests only carry the account script hash, fetch scripts to get the registry address
+        let account_script_hashes = raw_requests
+            .iter()
+            .map(|raw| {
+                let hash: H256 = raw.account_script_hash().unpack();
+                hash
+            })
+            .unique()
+            .collect::<Vec<_>>();
+        let scripts = self
+            .godwoken_async_client
+            .get_script_batch(account_script_hashes.clone())
+            .await?;
+        let registry_addresses = account_script_hashes
+            .into_iter()
+            .zip(scripts.into_iter())
+            .map(|(hash, script)| {
+                let address = script.and_then(|s| self.eoa_eth_address(&convertion::to_script(s)));
+                (hash, address)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let withdrawals = raw_requests
+            .into_iter()
+            .enumerate()
+            .map(|(index, raw)| {
+                let account_script_hash: H256 = raw.account_script_hash().unpack();
+                let registry_address = registry_addresses
+                    .get(&account_script_hash)
+                    .cloned()
+                    .flatten();
+                Withdrawal {
+                    block_number,
+                    block_hash,
+                    withdrawal_index: index as u32,
+                    account_script_hash: account_script_hash.0.into(),
+                    registry_id: raw.registry_id().unpack(),
+                    registry_address,
+                    capacity: raw.capacity().unpack(),
+                    sudt_script_hash: raw.sudt_script_hash().unpack(),
+                    amount: raw.amount().unpack(),
+                    owner_lock_hash: raw.owner_lock_hash().unpack(),
+                    nonce: raw.nonce().unpack(),
+                    fee: raw.fee().unpack(),
+                    chain_id: raw.chain_id().unpack(),
+                }
+            })
+            .collect();
 
-        Ok(hashmap)
+        Ok(withdrawals)
+    }
+
+    fn build_account(&self, id: u32, script_hash: H256, script: &Script) -> Account {
+        let code_hash: H256 = script.code_hash().unpack();
+        let args = script.args().raw_data();
+
+        let (account_type, eth_address) = if code_hash == self.eth_account_lock_hash {
+            (AccountType::EthEoa, self.eoa_eth_address(script))
+        } else if code_hash == self.polyjuice_type_script_hash {
+            // polyjuice contract args: rollup_type_hash(32 bytes) + creator_account_id(4 bytes) + eth_address(20 bytes),
+            // the polyjuice creator account has no address
+            let eth_address = if args.len() == 56 {
+                let mut buf = [0u8; 20];
+                buf.copy_from_slice(&args[36..56]);
+                Some(buf)
+            } else {
+                None
+            };
+            (AccountType::PolyjuiceContract, eth_address)
+        } else if code_hash == self.l2_sudt_type_script_hash {
+            (AccountType::Sudt, None)
+        } else {
+            (AccountType::Other, None)
+        };
+
+        Account {
+            id,
+            script_hash: script_hash.0,
+            code_hash: code_hash.0,
+            account_type,
+            eth_address,
+            script: script.clone(),
+        }
     }
 
     async fn insert_or_update_l2block(
@@ -463,86 +858,234 @@ impl Web3Indexer {
         l2_block: L2Block,
         is_update: bool,
     ) -> Result<(usize, usize)> {
+        let receipts = self.fetch_transaction_receipts(&l2_block).await?;
+        let web3_block_with_txs = self.convert_l2_block(&l2_block, &receipts).await?;
+        let mode = if is_update {
+            WriteMode::Update
+        } else {
+            WriteMode::Insert
+        };
+        self.write_web3_block(web3_block_with_txs, mode).await
+    }
+
+    async fn convert_l2_block(
+        &self,
+        l2_block: &L2Block,
+        receipts: &HashMap<gw_common::H256, Receipt>,
+    ) -> Result<Web3BlockWithTransactions> {
         let block_number = l2_block.raw().number().unpack();
         let block_hash: gw_common::H256 = blake2b_256(l2_block.raw().as_slice()).into();
-        // let mut cumulative_gas_used: u128 = 0;
-        let l2_transactions = l2_block.transactions();
-        let l2_transactions_vec: Vec<L2Transaction> = l2_transactions.into_iter().collect();
+        let l2_transactions_vec: Vec<L2Transaction> = l2_block.transactions().into_iter().collect();
+
+        let (id_script_hashmap, accounts) = self.batch_from_script(&l2_transactions_vec).await?;
+
+        let (txs, gw_transactions) = self.convert_transactions(
+            &l2_transactions_vec,
+            block_number,
+            block_hash,
+            &id_script_hashmap,
+            receipts,
+        )?;
+
+        let mut log_index_cursor: u32 = 0;
+        let mut transfer_index_cursor: u32 = 0;
+        let mut cumulative_gas_used: u128 = 0;
+        let mut total_gas_limit: u128 = 0;
+        let mut block_logs_bloom: Bloom = [0u8; 256];
+        let txs = txs
+            .into_iter()
+            .enumerate()
+            .map(|(tx_index, mut tx)| {
+                let transaction_index = tx_index as u32;
+                tx.tx.transaction_index = transaction_index;
+                // update log.transaction_index too
+                // Update tx.log.index
+                tx.logs = tx
+                    .logs
+                    .into_iter()
+                    .map(|mut log| {
+                        log.transaction_index = transaction_index;
+                        log.log_index += log_index_cursor;
+                        log
+                    })
+                    .collect();
+                tx.token_transfers = tx
+                    .token_transfers
+                    .into_iter()
+                    .map(|mut transfer| {
+                        transfer.transaction_index = transaction_index;
+                        transfer.transfer_index += transfer_index_cursor;
+                        transfer
+                    })
+                    .collect();
+                if let Some(gasless) = tx.gasless.as_mut() {
+                    gasless.transaction_index = transaction_index;
+                }
+                cumulative_gas_used += tx.tx.gas_used;
+                tx.tx.cumulative_gas_used = cumulative_gas_used;
+                tx.tx.logs_bloom = logs_bloom(&tx.logs);
+                merge_bloom(&mut block_logs_bloom, &tx.tx.logs_bloom);
+
+                total_gas_limit += tx.tx.gas_limit;
+                log_index_cursor += tx.logs.len() as u32;
+                transfer_index_cursor += tx.token_transfers.len() as u32;
+
+                tx
+            })
+            .collect::<Vec<_>>();
+
+        let block = self
+            .build_web3_block(
+                l2_block,
+                total_gas_limit,
+                cumulative_gas_used,
+                block_logs_bloom,
+            )
+            .await?;
+        let withdrawals = self.convert_withdrawals(l2_block, block_hash).await?;
+
+        Ok(Web3BlockWithTransactions {
+            block,
+            txs,
+            withdrawals,
+            gw_transactions,
+            accounts,
+        })
+    }
+
+    async fn write_web3_block(
+        &self,
+        web3_block_with_txs: Web3BlockWithTransactions,
+        mode: WriteMode,
+    ) -> Result<(usize, usize)> {
+        let account_ids = web3_block_with_txs
+            .accounts
+            .iter()
+            .map(|account| account.id)
+            .collect::<Vec<_>>();
+        let result = self.write_web3_block_rows(web3_block_with_txs, mode).await;
+        if result.is_err() {
+            // accounts of a block which isn't written are reported again by the next conversion
+            for id in account_ids {
+                self.account_cache.remove(id);
+            }
+        }
+        result
+    }
+
+    async fn write_web3_block_rows(
+        &self,
+        web3_block_with_txs: Web3BlockWithTransactions,
+        mode: WriteMode,
+    ) -> Result<(usize, usize)> {
+        let is_update = mode == WriteMode::Update;
+        let Web3BlockWithTransactions {
+            block: web3_block,
+            mut txs,
+            withdrawals,
+            gw_transactions,
+            accounts,
+        } = web3_block_with_txs;
+        let block_number = web3_block.number;
+        let token_transfers = txs
+            .iter_mut()
+            .flat_map(|tx| std::mem::take(&mut tx.token_transfers))
+            .collect::<Vec<_>>();
+        let gasless_txs = txs
+            .iter_mut()
+            .filter_map(|tx| tx.gasless.take())
+            .collect::<Vec<_>>();
+        let anomalies = if self.verify_signatures {
+            Some(check_signatures(&txs))
+        } else {
+            None
+        };
+        let erc_events = txs
+            .iter()
+            .flat_map(|tx| tx.logs.iter())
+            .map(decode_erc_events)
+            .fold(ErcEvents::default(), |mut acc, events| {
+                acc.extend(events);
+                acc
+            });
 
         let mut logs_len: usize = 0;
         let mut web3_txs_len: usize = 0;
 
-        let id_script_hashmap = self.batch_from_script(&l2_transactions_vec).await?;
-
-        let txs_slice = l2_transactions_vec
+        let txs_slice = txs
             .into_iter()
-            .chunks(TX_BATCH_SIZE)
+            .chunks(self.batch.txs)
             .into_iter()
             .map(|chunk| chunk.collect())
             .collect::<Vec<Vec<_>>>();
 
-        // begin db transaction
-        let pool = &*POOL;
+        // begin db transaction, the update command has its own pool
+        let pool = if is_update { &*POOL_FOR_UPDATE } else { &*POOL };
         let mut pg_tx = pool.begin().await?;
 
-        let mut tx_index_cursor: u32 = 0;
-        let mut log_index_cursor: u32 = 0;
-
-        let mut cumulative_gas_used: u128 = 0;
-        let mut total_gas_limit: u128 = 0;
-        for txs in txs_slice {
-            let l2_transaction_with_logs_vec = txs
-                .into_par_iter()
-                .map(|tx| {
-                    self.filter_single_transaction(tx, block_number, block_hash, &id_script_hashmap)
-                })
-                .collect::<Result<Vec<Option<Web3TransactionWithLogs>>>>()?;
-
-            let txs_vec = l2_transaction_with_logs_vec
-                .into_iter()
-                .flatten()
-                .enumerate()
-                .map(|(tx_index, mut tx)| {
-                    let transaction_index = tx_index as u32 + tx_index_cursor;
-                    tx.tx.transaction_index = transaction_index;
-                    // update log.transaction_index too
-                    // Update tx.log.index
-                    tx.logs = tx
-                        .logs
-                        .into_iter()
-                        .map(|mut log| {
-                            log.transaction_index = transaction_index;
-                            log.log_index += log_index_cursor;
-                            log
-                        })
-                        .collect();
-                    cumulative_gas_used += tx.tx.gas_used;
-                    tx.tx.cumulative_gas_used = cumulative_gas_used;
-
-                    total_gas_limit += tx.tx.gas_limit;
-                    log_index_cursor += tx.logs.len() as u32;
-
-                    tx
-                })
-                .collect::<Vec<_>>();
-
-            tx_index_cursor += txs_vec.len() as u32;
+        if mode == WriteMode::Replace {
+            delete_web3_block(block_number, &mut pg_tx).await?;
+        }
 
+        for txs_vec in txs_slice {
             // insert to db or update
             let (txs_part_len, logs_part_len) = if is_update {
-                update_web3_txs_and_logs(txs_vec, &mut pg_tx).await?
+                update_web3_txs_and_logs(txs_vec, self.batch.insert_rows, &mut pg_tx).await?
             } else {
-                insert_web3_txs_and_logs(txs_vec, &mut pg_tx).await?
+                insert_web3_txs_and_logs(txs_vec, self.batch.insert_rows, &mut pg_tx).await?
             };
 
             web3_txs_len += txs_part_len;
             logs_len += logs_part_len;
         }
 
+        // token transfers and erc events of a block are replaced as a whole when updating
+        if is_update {
+            delete_token_transfers(block_number, &mut pg_tx).await?;
+            delete_erc_events(block_number, &mut pg_tx).await?;
+        }
+        insert_token_transfers(token_transfers, self.batch.insert_rows, &mut pg_tx).await?;
+        insert_erc_events(erc_events, self.batch.insert_rows, &mut pg_tx).await?;
+
+        // gasless transactions of a block are replaced as a whole when updating
+        if is_update {
+            delete_gasless_transactions(block_number, &mut pg_tx).await?;
+        }
+        insert_gasless_transactions(gasless_txs, &mut pg_tx).await?;
+
+        // anomalies are only replaced when signatures are verified
+        if let Some(anomalies) = anomalies {
+            if !anomalies.is_empty() {
+                log::warn!(
+                    "web3 indexer: {} signature anomalies in block #{}",
+                    anomalies.len(),
+                    block_number
+                );
+            }
+            if is_update {
+                delete_anomalies(block_number, &mut pg_tx).await?;
+            }
+            insert_anomalies(anomalies, &mut pg_tx).await?;
+        }
+
+        // withdrawals of a block are replaced as a whole when updating
+        if is_update {
+            delete_withdrawals(block_number, &mut pg_tx).await?;
+        }
+        insert_withdrawals(withdrawals, self.batch.insert_rows, &mut pg_tx).await?;
+
+        // godwoken native transactions of a block are replaced as a whole when updating
+        if is_update {
+            delete_gw_transactions(block_number, &mut pg_tx).await?;
+        }
+        insert_gw_transactions(gw_transactions, self.batch.insert_rows, &mut pg_tx).await?;
+
+        // accounts already in the table keep their first seen block
+        if self.persist_accounts {
+            insert_accounts(&accounts, block_number, &mut pg_tx).await?;
+        }
+
         // insert or update block
-        let web3_block = self
-            .build_web3_block(&l2_block, total_gas_limit, cumulative_gas_used)
-            .await?;
         if is_update {
             update_web3_block(web3_block, &mut pg_tx).await?;
         } else {
@@ -555,46 +1098,85 @@ impl Web3Indexer {
         Ok((web3_txs_len, logs_len))
     }
 
-    fn get_transaction_receipt(
+    // Fetch receipts of all transactions in `l2_block` by batch requests,
+    // only the failed ones are requested again when retrying.
+    async fn fetch_transaction_receipts(
         &self,
-        gw_tx_hash: gw_common::H256,
-        block_number: u64,
-    ) -> Result<TxReceipt> {
-        let tx_hash = ckb_types::H256::from_slice(gw_tx_hash.as_slice())?;
-        let tx_hash_hex = hex(tx_hash.as_bytes())
-            .unwrap_or_else(|_| format!("convert tx hash: {:?} to hex format failed", tx_hash));
-
-        let get_receipt = || -> Result<TxReceipt> {
-            let tx_receipt: TxReceipt = self
-                .godwoken_rpc_client
-                .get_transaction_receipt(&tx_hash)?
-                .ok_or_else(|| {
-                    anyhow!(
-                        "tx receipt not found by tx_hash: ({}) of block: {}",
-                        tx_hash_hex,
-                        block_number,
-                    )
-                })?
-                .into();
-            Ok(tx_receipt)
-        };
+        l2_block: &L2Block,
+    ) -> Result<HashMap<gw_common::H256, Receipt>> {
+        let block_number: u64 = l2_block.raw().number().unpack();
+        let mut pending_tx_hashes = l2_block
+            .transactions()
+            .into_iter()
+            .map(|tx| tx.hash().into())
+            .collect::<Vec<gw_common::H256>>();
+        let mut receipts = HashMap::with_capacity(pending_tx_hashes.len());
 
-        let max_retry = 10;
+        let max_retry = self.retry.receipts;
         let mut retry_times = 0;
-        while retry_times < max_retry {
-            let receipt = get_receipt();
-            match receipt {
-                Ok(tx_receipt) => return Ok(tx_receipt),
-                Err(err) => {
-                    log::error!("{}", err);
-                    retry_times += 1;
-                    // sleep and retry
-                    let sleep_time = std::time::Duration::from_secs(retry_times);
-                    std::thread::sleep(sleep_time);
+        loop {
+            let mut failed_tx_hashes = vec![];
+            for gw_tx_hashes in pending_tx_hashes.chunks(self.batch.receipts) {
+                let tx_hashes = gw_tx_hashes
+                    .iter()
+                    .map(|h| ckb_types::H256::from_slice(h.as_slice()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let results = match self
+                    .godwoken_async_client
+                    .get_transaction_receipt_batch(&tx_hashes)
+                    .await
+                {
+                    Ok(results) => results,
+                    Err(err) if retry_times < max_retry => {
+                        log::error!("{}", err);
+                        failed_tx_hashes.extend_from_slice(gw_tx_hashes);
+                        continue;
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+
+                for (gw_tx_hash, result) in gw_tx_hashes.iter().zip(results.into_iter()) {
+                    match result {
+                        Ok(Some((tx_receipt, return_data))) => {
+                            let receipt = Receipt {
+                                tx_receipt: tx_receipt.into(),
+                                return_data: return_data.map(|data| data.as_bytes().to_vec()),
+                            };
+                            receipts.insert(*gw_tx_hash, receipt);
+                        }
+                        Ok(None) => {
+                            log::error!(
+                                "tx receipt not found by tx_hash: ({}) of block: {}",
+                                hex(gw_tx_hash.as_slice())?,
+                                block_number,
+                            );
+                            failed_tx_hashes.push(*gw_tx_hash);
+                        }
+                        Err(err) => {
+                            log::error!("{}", err);
+                            failed_tx_hashes.push(*gw_tx_hash);
+                        }
+                    }
                 }
             }
+
+            if failed_tx_hashes.is_empty() {
+                return Ok(receipts);
+            }
+            if retry_times >= max_retry {
+                return Err(anyhow!(
+                    "failed to get {} tx receipts of block: {}",
+                    failed_tx_hashes.len(),
+                    block_number
+                ));
+            }
+            retry_times += 1;
+            // sleep and retry
+            let sleep_time =
+                std::time::Duration::from_secs(retry_times * self.retry.receipt_backoff_secs);
+            smol::Timer::after(sleep_time).await;
+            pending_tx_hashes = failed_tx_hashes;
         }
-        get_receipt()
     }
 
     async fn build_web3_block(
@@ -602,6 +1184,7 @@ impl Web3Indexer {
         l2_block: &L2Block,
         gas_limit: u128,
         gas_used: u128,
+        logs_bloom: Bloom,
     ) -> Result<Web3Block> {
         let block_number = l2_block.raw().number().unpack();
         let block_hash: gw_common::H256 = l2_block.hash().into();
@@ -637,15 +1220,84 @@ impl Web3Indexer {
             miner: miner_address,
             size,
             timestamp: DateTime::<Utc>::from_utc(timestamp, Utc),
+            logs_bloom,
         };
         Ok(web3_block)
     }
 }
 
+// Collect SudtTransfer / SudtPayFee logs of a transaction, `transaction_index` and
+// `transfer_index` are updated later like logs.
+fn parse_token_transfers(
+    log_items: LogItemVec,
+    gw_tx_hash: gw_common::H256,
+    block_number: u64,
+    block_hash: gw_common::H256,
+) -> Result<Vec<TokenTransfer>> {
+    let mut token_transfers = vec![];
+    for log_item in log_items.into_iter() {
+        let (sudt_id, from_address, to_address, amount, kind) =
+            match parse_log(&log_item, &gw_tx_hash)? {
+                GwLog::SudtTransfer {
+                    sudt_id,
+                    from_address,
+                    to_address,
+                    amount,
+                } => (
+                    sudt_id,
+                    from_address,
+                    to_address,
+                    amount,
+                    TokenTransferKind::Transfer,
+                ),
+                GwLog::SudtPayFee {
+                    sudt_id,
+                    from_address,
+                    block_producer_address,
+                    amount,
+                } => (
+                    sudt_id,
+                    from_address,
+                    block_producer_address,
+                    amount,
+                    TokenTransferKind::Fee,
+                ),
+                _ => continue,
+            };
+        token_transfers.push(TokenTransfer {
+            transaction_hash: gw_tx_hash,
+            transaction_index: 0, // should update later
+            block_number,
+            block_hash,
+            sudt_id,
+            from_address,
+            to_address,
+            amount,
+            kind,
+            transfer_index: token_transfers.len() as u32,
+        });
+    }
+    Ok(token_transfers)
+}
+
+fn find_receipt(
+    receipts: &HashMap<gw_common::H256, Receipt>,
+    gw_tx_hash: &gw_common::H256,
+    block_number: u64,
+) -> Result<Receipt> {
+    receipts.get(gw_tx_hash).cloned().ok_or_else(|| {
+        anyhow!(
+            "tx receipt not prefetched, tx_hash: {}, block: {}",
+            hex(gw_tx_hash.as_slice()).unwrap_or_default(),
+            block_number
+        )
+    })
+}
+
 async fn batch_account_id_to_script(
     godwoken_async_client: &GodwokenAsyncClient,
     account_ids: Vec<u32>,
-) -> Result<Vec<Option<Script>>> {
+) -> Result<Vec<Option<(H256, Script)>>> {
     if account_ids.is_empty() {
         return Ok(vec![]);
     }
@@ -654,13 +1306,164 @@ async fn batch_account_id_to_script(
         .get_script_hash_batch(account_ids)
         .await?;
     let scripts = godwoken_async_client
-        .get_script_batch(script_hashes)
+        .get_script_batch(script_hashes.clone())
         .await?;
 
     let result = scripts
         .into_iter()
-        .map(|script| script.map(convertion::to_script))
+        .zip(script_hashes.into_iter())
+        .map(|(script, script_hash)| script.map(|s| (script_hash, convertion::to_script(s))))
         .collect::<Vec<_>>();
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use gw_types::{core::ScriptHashType, packed::RawL2Transaction};
+
+    use super::*;
+
+    const ROLLUP_TYPE_HASH: [u8; 32] = [1u8; 32];
+    const ETH_LOCK_HASH: [u8; 32] = [2u8; 32];
+    const TRON_LOCK_HASH: [u8; 32] = [3u8; 32];
+    const UNKNOWN_LOCK_HASH: [u8; 32] = [4u8; 32];
+    const META_CONTRACT_HASH: [u8; 32] = [5u8; 32];
+
+    fn indexer() -> Web3Indexer {
+        let eoa_locks = vec![
+            EoaLockConfig {
+                eoa_type: "eth".to_string(),
+                type_hash: H256(ETH_LOCK_HASH),
+                address_layout: Some(EoaAddressLayout::RollupTypeHashPrefixed),
+                web3_visible: true,
+            },
+            EoaLockConfig {
+                eoa_type: "tron".to_string(),
+                type_hash: H256(TRON_LOCK_HASH),
+                address_layout: None,
+                web3_visible: false,
+            },
+        ];
+        Web3Indexer::new(
+            H256([6u8; 32]),
+            H256([7u8; 32]),
+            H256(ROLLUP_TYPE_HASH),
+            H256(ETH_LOCK_HASH),
+            eoa_locks,
+            "http://127.0.0.1:8119",
+            16,
+            false,
+            false,
+            None,
+            BatchConfig::default(),
+            RetryConfig::default(),
+        )
+        .unwrap()
+    }
+
+    fn script(code_hash: [u8; 32], args: Vec<u8>) -> Script {
+        Script::new_builder()
+            .code_hash(code_hash.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(args).pack())
+            .build()
+    }
+
+    fn l2_transaction(from_id: u32, to_id: u32) -> L2Transaction {
+        let raw = RawL2Transaction::new_builder()
+            .chain_id(71402u64.pack())
+            .from_id(from_id.pack())
+            .to_id(to_id.pack())
+            .nonce(1u32.pack())
+            .args(Bytes::from(vec![0xffu8, 0x01]).pack())
+            .build();
+        L2Transaction::new_builder().raw(raw).build()
+    }
+
+    #[test]
+    fn test_convert_skipped_transactions() {
+        let eth_script = script(
+            ETH_LOCK_HASH,
+            [ROLLUP_TYPE_HASH.to_vec(), vec![0x11u8; 20]].concat(),
+        );
+        let tron_script = script(TRON_LOCK_HASH, vec![0x22u8; 20]);
+        let unknown_script = script(UNKNOWN_LOCK_HASH, vec![0x33u8; 20]);
+        let id_script_map: HashMap<u32, Option<Script>> = [
+            (0, Some(script(META_CONTRACT_HASH, vec![]))),
+            (2, Some(eth_script.clone())),
+            (3, Some(tron_script.clone())),
+            (4, Some(unknown_script.clone())),
+        ]
+        .into_iter()
+        .collect();
+        // an eth EOA calling the meta contract, a non-ETH EOA and an unknown lock
+        let l2_transactions = vec![
+            l2_transaction(2, 0),
+            l2_transaction(3, 0),
+            l2_transaction(4, 0),
+        ];
+        let receipts: HashMap<gw_common::H256, Receipt> = l2_transactions
+            .iter()
+            .map(|tx| {
+                let receipt = Receipt {
+                    tx_receipt: TxReceipt::new_builder().build(),
+                    return_data: None,
+                };
+                (tx.hash().into(), receipt)
+            })
+            .collect();
+
+        let (web3_txs, gw_transactions) = indexer()
+            .convert_transactions(
+                &l2_transactions,
+                10,
+                gw_common::H256::zero(),
+                &id_script_map,
+                &receipts,
+            )
+            .unwrap();
+        assert!(web3_txs.is_empty());
+        assert_eq!(gw_transactions.len(), 3);
+
+        let expected = [
+            (eth_script, 2, Some("eth")),
+            (tron_script, 3, Some("tron")),
+            (unknown_script, 4, None),
+        ];
+        for (index, (tx, (from_script, from_id, eoa_type))) in
+            gw_transactions.iter().zip(expected.iter()).enumerate()
+        {
+            let hash: gw_common::H256 = l2_transactions[index].hash().into();
+            assert_eq!(tx.hash, hash);
+            assert_eq!(tx.block_number, 10);
+            assert_eq!(tx.l2_tx_index, index as u32);
+            assert_eq!(tx.from_id, *from_id);
+            assert_eq!(tx.to_id, 0);
+            assert_eq!(tx.nonce, 1);
+            assert_eq!(tx.args, vec![0xffu8, 0x01]);
+            assert_eq!(
+                tx.from_lock_hash.as_slice(),
+                blake2b_256(from_script.as_slice())
+            );
+            assert_eq!(tx.eoa_type.as_deref(), *eoa_type);
+            assert_eq!(tx.exit_code, 0);
+        }
+    }
+
+    #[test]
+    fn test_convert_transaction_from_missing_account() {
+        let id_script_map: HashMap<u32, Option<Script>> = [(5, None)].into_iter().collect();
+        let l2_transactions = vec![l2_transaction(5, 0)];
+        let receipts = HashMap::new();
+        assert!(indexer()
+            .convert_transactions(
+                &l2_transactions,
+                10,
+                gw_common::H256::zero(),
+                &id_script_map,
+                &receipts,
+            )
+            .is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use std::{convert::TryFrom, str::FromStr};
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use gw_types::U256;
 use rust_decimal::Decimal;
 use sqlx::{
@@ -15,8 +15,12 @@ use sqlx::{Postgres, QueryBuilder};
 
 use crate::{
     cpu_count::CPU_COUNT,
-    pool::POOL_FOR_UPDATE,
-    types::{Block, Log, Transaction, TransactionWithLogs},
+    erc_events::ErcEvents,
+    helper::hex,
+    types::{
+        AccessListItem, Block, GwTransaction, Log, TokenTransfer, Transaction, TransactionWithLogs,
+        Withdrawal,
+    },
 };
 
 use itertools::Itertools;
@@ -24,7 +28,18 @@ use rayon::prelude::*;
 
 extern crate num_cpus;
 
-const INSERT_LOGS_BATCH_SIZE: usize = 5000;
+// Tables keyed by `block_number`, in the order they are deleted when blocks are rolled back
+pub const BLOCK_DERIVED_TABLES: [&str; 9] = [
+    "gw_transactions",
+    "gasless_transactions",
+    "anomalies",
+    "withdrawals",
+    "erc_transfers",
+    "erc_approvals",
+    "token_transfers",
+    "logs",
+    "transactions",
+];
 
 pub struct DbBlock<'a> {
     number: Decimal,
@@ -35,6 +50,7 @@ pub struct DbBlock<'a> {
     timestamp: DateTime<Utc>,
     miner: &'a [u8],
     size: Decimal,
+    logs_bloom: &'a [u8],
 }
 
 impl<'a> TryFrom<&'a Block> for DbBlock<'a> {
@@ -50,6 +66,7 @@ impl<'a> TryFrom<&'a Block> for DbBlock<'a> {
             timestamp: block.timestamp,
             miner: block.miner.as_ref(),
             size: Decimal::from(block.size),
+            logs_bloom: block.logs_bloom.as_ref(),
         };
         Ok(a)
     }
@@ -77,6 +94,14 @@ pub struct DbTransaction {
     contract_address: Option<Vec<u8>>,
     exit_code: Decimal,
     chain_id: Option<Decimal>,
+    status_code: Option<i32>,
+    failure_reason: Option<String>,
+    revert_data: Option<Vec<u8>>,
+    logs_bloom: Vec<u8>,
+    tx_type: i16,
+    access_list: Option<String>,
+    max_priority_fee_per_gas: Option<BigDecimal>,
+    max_fee_per_gas: Option<BigDecimal>,
 }
 
 impl TryFrom<Transaction> for DbTransaction {
@@ -85,6 +110,22 @@ impl TryFrom<Transaction> for DbTransaction {
     fn try_from(tx: Transaction) -> Result<DbTransaction, Self::Error> {
         let web3_to_address = tx.to_address.map(|addr| addr.to_vec());
         let web3_contract_address = tx.contract_address.map(|addr| addr.to_vec());
+        let access_list = tx
+            .access_list
+            .as_deref()
+            .map(access_list_to_json)
+            .transpose()?;
+        let max_priority_fee_per_gas = tx
+            .max_priority_fee_per_gas
+            .as_ref()
+            .map(u128_to_big_decimal)
+            .transpose()?;
+        let max_fee_per_gas = tx
+            .max_fee_per_gas
+            .as_ref()
+            .map(u128_to_big_decimal)
+            .transpose()?;
+        let failure_reason = tx.failure_reason();
         let db_transaction = Self {
             hash: tx.gw_tx_hash.as_slice().to_vec(),
             eth_tx_hash: tx.compute_eth_tx_hash().as_slice().to_vec(),
@@ -106,6 +147,14 @@ impl TryFrom<Transaction> for DbTransaction {
             contract_address: web3_contract_address,
             exit_code: tx.exit_code.into(),
             chain_id: tx.chain_id.map(|id| id.into()),
+            status_code: tx.status_code,
+            failure_reason,
+            revert_data: tx.revert_data,
+            logs_bloom: tx.logs_bloom.to_vec(),
+            tx_type: tx.tx_type as i16,
+            access_list,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
         };
         Ok(db_transaction)
     }
@@ -147,6 +196,44 @@ impl DbLog {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct DbTokenTransfer {
+    transaction_hash: Vec<u8>,
+    transaction_index: Decimal,
+    block_number: Decimal,
+    block_hash: Vec<u8>,
+    transfer_index: Decimal,
+    sudt_id: i64,
+    from_registry_id: i64,
+    from_address: Vec<u8>,
+    to_registry_id: i64,
+    to_address: Vec<u8>,
+    amount: BigDecimal,
+    kind: &'static str,
+}
+
+impl TryFrom<TokenTransfer> for DbTokenTransfer {
+    type Error = anyhow::Error;
+
+    fn try_from(transfer: TokenTransfer) -> Result<DbTokenTransfer, Self::Error> {
+        let db_token_transfer = Self {
+            transaction_hash: transfer.transaction_hash.as_slice().to_vec(),
+            transaction_index: transfer.transaction_index.into(),
+            block_number: transfer.block_number.into(),
+            block_hash: transfer.block_hash.as_slice().to_vec(),
+            transfer_index: transfer.transfer_index.into(),
+            sudt_id: transfer.sudt_id.into(),
+            from_registry_id: transfer.from_address.registry_id.into(),
+            from_address: transfer.from_address.address,
+            to_registry_id: transfer.to_address.registry_id.into(),
+            to_address: transfer.to_address.address,
+            amount: u256_to_big_decimal(&transfer.amount)?,
+            kind: transfer.kind.as_str(),
+        };
+        Ok(db_token_transfer)
+    }
+}
+
 pub async fn insert_web3_block(
     web3_block: Block,
     pg_tx: &mut sqlx::Transaction<'_, Postgres>,
@@ -154,7 +241,7 @@ pub async fn insert_web3_block(
     let block = DbBlock::try_from(&web3_block)?;
 
     sqlx::query(
-        "INSERT INTO blocks (number, hash, parent_hash, gas_limit, gas_used, timestamp, miner, size) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+        "INSERT INTO blocks (number, hash, parent_hash, gas_limit, gas_used, timestamp, miner, size, logs_bloom) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"
     )
         .bind(block.number)
         .bind(block.hash)
@@ -164,6 +251,7 @@ pub async fn insert_web3_block(
         .bind(block.timestamp)
         .bind(block.miner)
         .bind(block.size)
+        .bind(block.logs_bloom)
         .execute(pg_tx)
         .await?;
 
@@ -172,6 +260,7 @@ pub async fn insert_web3_block(
 
 pub async fn insert_web3_txs_and_logs(
     web3_tx_with_logs_vec: Vec<TransactionWithLogs>,
+    insert_batch_size: usize,
     pg_tx: &mut sqlx::Transaction<'_, Postgres>,
 ) -> Result<(usize, usize)> {
     if web3_tx_with_logs_vec.is_empty() {
@@ -200,14 +289,14 @@ pub async fn insert_web3_txs_and_logs(
 
     let logs_slice = logs
         .into_iter()
-        .chunks(INSERT_LOGS_BATCH_SIZE)
+        .chunks(insert_batch_size)
         .into_iter()
         .map(|chunk| chunk.collect())
         .collect::<Vec<Vec<_>>>();
 
     let mut txs_query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
                 "INSERT INTO transactions
-                (hash, eth_tx_hash, block_number, block_hash, transaction_index, from_address, to_address, value, nonce, gas_limit, gas_price, input, v, r, s, cumulative_gas_used, gas_used, contract_address, exit_code, chain_id) "
+                (hash, eth_tx_hash, block_number, block_hash, transaction_index, from_address, to_address, value, nonce, gas_limit, gas_price, input, v, r, s, cumulative_gas_used, gas_used, contract_address, exit_code, chain_id, status_code, failure_reason, revert_data, logs_bloom, type, access_list, max_priority_fee_per_gas, max_fee_per_gas) "
             );
 
     txs_query_builder
@@ -231,7 +320,15 @@ pub async fn insert_web3_txs_and_logs(
                 .push_bind(tx.gas_used)
                 .push_bind(tx.contract_address)
                 .push_bind(tx.exit_code)
-                .push_bind(tx.chain_id);
+                .push_bind(tx.chain_id)
+                .push_bind(tx.status_code)
+                .push_bind(tx.failure_reason)
+                .push_bind(tx.revert_data)
+                .push_bind(tx.logs_bloom)
+                .push_bind(tx.tx_type)
+                .push_bind(tx.access_list)
+                .push_bind(tx.max_priority_fee_per_gas)
+                .push_bind(tx.max_fee_per_gas);
         })
         .push(" RETURNING id");
 
@@ -281,6 +378,299 @@ pub async fn insert_web3_txs_and_logs(
     Ok((txs_len, logs_len))
 }
 
+pub async fn insert_token_transfers(
+    token_transfers: Vec<TokenTransfer>,
+    insert_batch_size: usize,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<usize> {
+    if token_transfers.is_empty() {
+        return Ok(0);
+    }
+
+    let token_transfers = token_transfers
+        .into_iter()
+        .map(DbTokenTransfer::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    let token_transfers_len = token_transfers.len();
+
+    let token_transfers_slice = token_transfers
+        .into_iter()
+        .chunks(insert_batch_size)
+        .into_iter()
+        .map(|chunk| chunk.collect())
+        .collect::<Vec<Vec<_>>>();
+
+    for db_token_transfers in token_transfers_slice {
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO token_transfers
+            (transaction_hash, transaction_index, block_number, block_hash, transfer_index, sudt_id, from_registry_id, from_address, to_registry_id, to_address, amount, kind)"
+        );
+        query_builder.push_values(db_token_transfers, |mut b, transfer| {
+            b.push_bind(transfer.transaction_hash)
+                .push_bind(transfer.transaction_index)
+                .push_bind(transfer.block_number)
+                .push_bind(transfer.block_hash)
+                .push_bind(transfer.transfer_index)
+                .push_bind(transfer.sudt_id)
+                .push_bind(transfer.from_registry_id)
+                .push_bind(transfer.from_address)
+                .push_bind(transfer.to_registry_id)
+                .push_bind(transfer.to_address)
+                .push_bind(transfer.amount)
+                .push_bind(transfer.kind);
+        });
+        query_builder.build().execute(&mut (*pg_tx)).await?;
+    }
+
+    Ok(token_transfers_len)
+}
+
+pub async fn delete_token_transfers(
+    block_number: u64,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<()> {
+    sqlx::query("DELETE FROM token_transfers WHERE block_number = $1")
+        .bind(Decimal::from(block_number))
+        .execute(pg_tx)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert_erc_events(
+    erc_events: ErcEvents,
+    insert_batch_size: usize,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<()> {
+    let ErcEvents {
+        transfers,
+        approvals,
+    } = erc_events;
+
+    for transfers in transfers
+        .into_iter()
+        .chunks(insert_batch_size)
+        .into_iter()
+        .map(|chunk| chunk.collect::<Vec<_>>())
+    {
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO erc_transfers
+            (transaction_hash, transaction_index, block_number, block_hash, log_index, batch_index, standard, token_contract, operator, from_address, to_address, token_id, amount)"
+        );
+        let mut values = vec![];
+        for transfer in transfers {
+            let token_id = transfer
+                .token_id
+                .as_ref()
+                .map(uint_to_big_decimal)
+                .transpose()?;
+            let amount = uint_to_big_decimal(&transfer.amount)?;
+            values.push((transfer, token_id, amount));
+        }
+        query_builder.push_values(values, |mut b, (transfer, token_id, amount)| {
+            b.push_bind(transfer.transaction_hash.as_slice().to_vec())
+                .push_bind(Decimal::from(transfer.transaction_index))
+                .push_bind(Decimal::from(transfer.block_number))
+                .push_bind(transfer.block_hash.as_slice().to_vec())
+                .push_bind(Decimal::from(transfer.log_index))
+                .push_bind(Decimal::from(transfer.batch_index))
+                .push_bind(transfer.standard.as_str())
+                .push_bind(transfer.token_contract.to_vec())
+                .push_bind(transfer.operator.map(|addr| addr.to_vec()))
+                .push_bind(transfer.from_address.to_vec())
+                .push_bind(transfer.to_address.to_vec())
+                .push_bind(token_id)
+                .push_bind(amount);
+        });
+        query_builder.build().execute(&mut (*pg_tx)).await?;
+    }
+
+    for approvals in approvals
+        .into_iter()
+        .chunks(insert_batch_size)
+        .into_iter()
+        .map(|chunk| chunk.collect::<Vec<_>>())
+    {
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO erc_approvals
+            (transaction_hash, transaction_index, block_number, block_hash, log_index, standard, token_contract, owner, spender, token_id, amount)"
+        );
+        let mut values = vec![];
+        for approval in approvals {
+            let token_id = approval
+                .token_id
+                .as_ref()
+                .map(uint_to_big_decimal)
+                .transpose()?;
+            let amount = approval
+                .amount
+                .as_ref()
+                .map(uint_to_big_decimal)
+                .transpose()?;
+            values.push((approval, token_id, amount));
+        }
+        query_builder.push_values(values, |mut b, (approval, token_id, amount)| {
+            b.push_bind(approval.transaction_hash.as_slice().to_vec())
+                .push_bind(Decimal::from(approval.transaction_index))
+                .push_bind(Decimal::from(approval.block_number))
+                .push_bind(approval.block_hash.as_slice().to_vec())
+                .push_bind(Decimal::from(approval.log_index))
+                .push_bind(approval.standard.as_str())
+                .push_bind(approval.token_contract.to_vec())
+                .push_bind(approval.owner.to_vec())
+                .push_bind(approval.spender.to_vec())
+                .push_bind(token_id)
+                .push_bind(amount);
+        });
+        query_builder.build().execute(&mut (*pg_tx)).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn delete_erc_events(
+    block_number: u64,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<()> {
+    let number = Decimal::from(block_number);
+    sqlx::query("DELETE FROM erc_transfers WHERE block_number = $1")
+        .bind(number)
+        .execute(&mut *pg_tx)
+        .await?;
+    sqlx::query("DELETE FROM erc_approvals WHERE block_number = $1")
+        .bind(number)
+        .execute(&mut *pg_tx)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert_withdrawals(
+    withdrawals: Vec<Withdrawal>,
+    insert_batch_size: usize,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<usize> {
+    if withdrawals.is_empty() {
+        return Ok(0);
+    }
+    let withdrawals_len = withdrawals.len();
+
+    for withdrawals in withdrawals
+        .into_iter()
+        .chunks(insert_batch_size)
+        .into_iter()
+        .map(|chunk| chunk.collect::<Vec<_>>())
+    {
+        let mut values = vec![];
+        for withdrawal in withdrawals {
+            let amount = u128_to_big_decimal(&withdrawal.amount)?;
+            let fee = u128_to_big_decimal(&withdrawal.fee)?;
+            values.push((withdrawal, amount, fee));
+        }
+
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO withdrawals
+            (block_number, block_hash, withdrawal_index, account_script_hash, registry_id, registry_address, capacity, sudt_script_hash, amount, owner_lock_hash, nonce, fee, chain_id)"
+        );
+        query_builder.push_values(values, |mut b, (withdrawal, amount, fee)| {
+            b.push_bind(Decimal::from(withdrawal.block_number))
+                .push_bind(withdrawal.block_hash.as_slice().to_vec())
+                .push_bind(Decimal::from(withdrawal.withdrawal_index))
+                .push_bind(withdrawal.account_script_hash.as_slice().to_vec())
+                .push_bind(i64::from(withdrawal.registry_id))
+                .push_bind(withdrawal.registry_address.map(|addr| addr.to_vec()))
+                .push_bind(Decimal::from(withdrawal.capacity))
+                .push_bind(withdrawal.sudt_script_hash.as_slice().to_vec())
+                .push_bind(amount)
+                .push_bind(withdrawal.owner_lock_hash.as_slice().to_vec())
+                .push_bind(i64::from(withdrawal.nonce))
+                .push_bind(fee)
+                .push_bind(Decimal::from(withdrawal.chain_id));
+        });
+        query_builder.build().execute(&mut (*pg_tx)).await?;
+    }
+
+    Ok(withdrawals_len)
+}
+
+pub async fn delete_withdrawals(
+    block_number: u64,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<()> {
+    sqlx::query("DELETE FROM withdrawals WHERE block_number = $1")
+        .bind(Decimal::from(block_number))
+        .execute(pg_tx)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert_gw_transactions(
+    gw_transactions: Vec<GwTransaction>,
+    insert_batch_size: usize,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<usize> {
+    if gw_transactions.is_empty() {
+        return Ok(0);
+    }
+    let gw_transactions_len = gw_transactions.len();
+
+    for gw_transactions in gw_transactions
+        .into_iter()
+        .chunks(insert_batch_size)
+        .into_iter()
+        .map(|chunk| chunk.collect::<Vec<_>>())
+    {
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO gw_transactions
+            (hash, block_number, block_hash, l2_tx_index, from_id, to_id, nonce, args, from_lock_hash, eoa_type, exit_code)"
+        );
+        query_builder.push_values(gw_transactions, |mut b, tx| {
+            b.push_bind(tx.hash.as_slice().to_vec())
+                .push_bind(Decimal::from(tx.block_number))
+                .push_bind(tx.block_hash.as_slice().to_vec())
+                .push_bind(Decimal::from(tx.l2_tx_index))
+                .push_bind(i64::from(tx.from_id))
+                .push_bind(i64::from(tx.to_id))
+                .push_bind(i64::from(tx.nonce))
+                .push_bind(tx.args)
+                .push_bind(tx.from_lock_hash.as_slice().to_vec())
+                .push_bind(tx.eoa_type)
+                .push_bind(Decimal::from(tx.exit_code));
+        });
+        query_builder.build().execute(&mut (*pg_tx)).await?;
+    }
+
+    Ok(gw_transactions_len)
+}
+
+pub async fn delete_gw_transactions(
+    block_number: u64,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<()> {
+    sqlx::query("DELETE FROM gw_transactions WHERE block_number = $1")
+        .bind(Decimal::from(block_number))
+        .execute(pg_tx)
+        .await?;
+    Ok(())
+}
+
+// Delete a block and all rows derived from it.
+pub async fn delete_web3_block(
+    block_number: u64,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<()> {
+    let number = Decimal::from(block_number);
+    for table in BLOCK_DERIVED_TABLES {
+        sqlx::query(&format!("DELETE FROM {} WHERE block_number = $1", table))
+            .bind(number)
+            .execute(&mut *pg_tx)
+            .await?;
+    }
+    sqlx::query("DELETE FROM blocks WHERE number = $1")
+        .bind(number)
+        .execute(&mut *pg_tx)
+        .await?;
+    Ok(())
+}
+
 pub async fn update_web3_block(
     web3_block: Block,
     pg_tx: &mut sqlx::Transaction<'_, Postgres>,
@@ -288,7 +678,7 @@ pub async fn update_web3_block(
     let block = DbBlock::try_from(&web3_block)?;
 
     sqlx::query(
-        "UPDATE blocks SET hash = $1, parent_hash = $2, gas_limit = $3, gas_used = $4, timestamp = $5, miner = $6, size = $7 where number = $8"
+        "UPDATE blocks SET hash = $1, parent_hash = $2, gas_limit = $3, gas_used = $4, timestamp = $5, miner = $6, size = $7, logs_bloom = $8 where number = $9"
     )
         .bind(block.hash)
         .bind(block.parent_hash)
@@ -297,6 +687,7 @@ pub async fn update_web3_block(
         .bind(block.timestamp)
         .bind(block.miner)
         .bind(block.size)
+        .bind(block.logs_bloom)
         .bind(block.number)
         .execute(pg_tx)
         .await?;
@@ -306,24 +697,43 @@ pub async fn update_web3_block(
 
 pub async fn update_web3_txs_and_logs(
     web3_tx_with_logs_vec: Vec<TransactionWithLogs>,
-    _pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+    insert_batch_size: usize,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
 ) -> Result<(usize, usize)> {
     if web3_tx_with_logs_vec.is_empty() {
         return Ok((0, 0));
     }
 
-    let (txs, logs) = web3_tx_with_logs_vec
-        .into_par_iter()
-        .enumerate()
-        .map(|(i, web3_tx_with_logs)| {
-            let db_logs: Result<Vec<DbLog>> = web3_tx_with_logs
-                .logs
-                .into_par_iter()
-                .map(|l| DbLog::try_from_log(l, i as i64))
-                .collect();
-            (DbTransaction::try_from(web3_tx_with_logs.tx), db_logs)
-        })
-        .collect::<(Vec<_>, Vec<_>)>();
+    // rows are converted in parallel, statements run one by one on `pg_tx` so the
+    // update of a block stays in a single transaction
+    let threads: usize = if let Some(cpu_num) = CPU_COUNT.get().copied() {
+        cpu_num
+    } else {
+        let cpu_count = num_cpus::get();
+        let size = cpu_count / 2;
+        if size > 0 {
+            size
+        } else {
+            1
+        }
+    };
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+    let (txs, logs) = thread_pool.install(|| {
+        web3_tx_with_logs_vec
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, web3_tx_with_logs)| {
+                let db_logs: Result<Vec<DbLog>> = web3_tx_with_logs
+                    .logs
+                    .into_par_iter()
+                    .map(|l| DbLog::try_from_log(l, i as i64))
+                    .collect();
+                (DbTransaction::try_from(web3_tx_with_logs.tx), db_logs)
+            })
+            .collect::<(Vec<_>, Vec<_>)>()
+    });
     let txs = txs.into_iter().collect::<Result<Vec<_>>>()?;
     let logs = logs.into_iter().collect::<Result<Vec<_>>>()?;
     let logs = logs.into_iter().flatten().collect::<Vec<_>>();
@@ -331,101 +741,55 @@ pub async fn update_web3_txs_and_logs(
     let logs_len = logs.len();
     let txs_len = txs.len();
 
-    let size = logs_len / 4;
-    let final_size = if size > INSERT_LOGS_BATCH_SIZE || size == 0 {
-        INSERT_LOGS_BATCH_SIZE
-    } else {
-        size
-    };
-
-    let logs_slice = logs
-        .into_iter()
-        .chunks(final_size)
-        .into_iter()
-        .map(|chunk| chunk.collect())
-        .collect::<Vec<Vec<_>>>();
+    for tx in txs {
+        sqlx::query(
+            "UPDATE transactions SET hash = $1, eth_tx_hash = $2, from_address = $3, to_address = $4, value = $5, nonce = $6, gas_limit = $7, gas_price = $8, input = $9, v = $10, r = $11, s = $12, cumulative_gas_used = $13, gas_used = $14, contract_address = $15, exit_code = $16, chain_id = $17, status_code = $18, failure_reason = $19, revert_data = $20, logs_bloom = $21, type = $22, access_list = $23, max_priority_fee_per_gas = $24, max_fee_per_gas = $25 where block_number = $26 and transaction_index = $27"
+        )
+        .bind(tx.hash)
+        .bind(tx.eth_tx_hash)
+        .bind(tx.from_address)
+        .bind(tx.to_address)
+        .bind(tx.value)
+        .bind(tx.nonce)
+        .bind(tx.gas_limit)
+        .bind(tx.gas_price)
+        .bind(tx.input)
+        .bind(tx.v)
+        .bind(tx.r)
+        .bind(tx.s)
+        .bind(tx.cumulative_gas_used)
+        .bind(tx.gas_used)
+        .bind(tx.contract_address)
+        .bind(tx.exit_code)
+        .bind(tx.chain_id)
+        .bind(tx.status_code)
+        .bind(tx.failure_reason)
+        .bind(tx.revert_data)
+        .bind(tx.logs_bloom)
+        .bind(tx.tx_type)
+        .bind(tx.access_list)
+        .bind(tx.max_priority_fee_per_gas)
+        .bind(tx.max_fee_per_gas)
+        .bind(tx.block_number)
+        .bind(tx.transaction_index)
+        .execute(&mut *pg_tx)
+        .await?;
+    }
 
-    futures::future::join_all(
-        txs.into_iter().map(|tx| {
-                sqlx::query(
-                    "UPDATE transactions SET hash = $1, eth_tx_hash = $2, from_address = $3, to_address = $4, value = $5, nonce = $6, gas_limit = $7, gas_price = $8, input = $9, v = $10, r = $11, s = $12, cumulative_gas_used = $13, gas_used = $14, contract_address = $15, exit_code = $16, chain_id = $17 where block_number = $18 and transaction_index = $19"
-                )
-                        .bind(tx.hash)
-                            .bind(tx.eth_tx_hash)
-                            .bind(tx.from_address)
-                            .bind(tx.to_address)
-                            .bind(tx.value)
-                            .bind(tx.nonce)
-                            .bind(tx.gas_limit)
-                            .bind(tx.gas_price)
-                            .bind(tx.input)
-                            .bind(tx.v)
-                            .bind(tx.r)
-                            .bind(tx.s)
-                            .bind(tx.cumulative_gas_used)
-                            .bind(tx.gas_used)
-                            .bind(tx.contract_address)
-                            .bind(tx.exit_code)
-                            .bind(tx.chain_id)
-                            .bind(tx.block_number)
-                            .bind(tx.transaction_index)
-                            .execute(&*POOL_FOR_UPDATE)
+    for db_logs in logs.into_iter().chunks(insert_batch_size).into_iter() {
+        let mut logs_query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+            "UPDATE logs SET transaction_hash = data_table.transaction_hash, address = data_table.address, data = data_table.data, topics = data_table.topics FROM ( "
+        );
+        logs_query_builder.push_values(db_logs, |mut b, log| {
+            b.push_bind(log.transaction_hash)
+                .push_bind(log.address)
+                .push_bind(log.data)
+                .push_bind(log.topics)
+                .push_bind(log.block_number)
+                .push_bind(log.log_index);
         })
-    )
-    .await
-    .into_iter()
-    .collect::<Result<Vec<_>, sqlx::Error>>()?;
-
-    if logs_len != 0 {
-        let logs_querys = logs_slice
-        .into_par_iter()
-        .map(|db_logs| {
-            let mut logs_query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
-                "UPDATE logs SET transaction_hash = data_table.transaction_hash, address = data_table.address, data = data_table.data, topics = data_table.topics FROM ( "
-            );
-
-            logs_query_builder.push_values(db_logs, |mut b, log| {
-                b.push_bind(log.transaction_hash)
-                    .push_bind(log.address)
-                    .push_bind(log.data)
-                    .push_bind(log.topics)
-                    .push_bind(log.block_number)
-                    .push_bind(log.log_index);
-            })
-            .push(" ) AS data_table(transaction_hash, address, data, topics, block_number, log_index) WHERE logs.block_number = data_table.block_number AND logs.log_index = data_table.log_index");
-            logs_query_builder
-        }).collect::<Vec<_>>();
-
-        let logs_slice_size: usize = if let Some(cpu_num) = *CPU_COUNT {
-            cpu_num
-        } else {
-            let cpu_count = num_cpus::get();
-            let size = cpu_count / 2;
-            if size > 0 {
-                size
-            } else {
-                1
-            }
-        };
-
-        let logs_query_slice = logs_querys
-            .into_iter()
-            .chunks(logs_slice_size)
-            .into_iter()
-            .map(|chunk| chunk.collect())
-            .collect::<Vec<Vec<_>>>();
-
-        for mut query_builder_vec in logs_query_slice {
-            query_builder_vec
-                .par_iter_mut()
-                .map(|query_builder| {
-                    let query = query_builder.build();
-                    smol::block_on(query.execute(&*POOL_FOR_UPDATE)).map_err(|err| anyhow!(err))
-                })
-                .collect::<Vec<_>>()
-                .into_iter()
-                .collect::<Result<Vec<_>>>()?;
-        }
+        .push(" ) AS data_table(transaction_hash, address, data, topics, block_number, log_index) WHERE logs.block_number = data_table.block_number AND logs.log_index = data_table.log_index");
+        logs_query_builder.build().execute(&mut *pg_tx).await?;
     }
 
     Ok((txs_len, logs_len))
@@ -440,3 +804,27 @@ fn u256_to_big_decimal(value: &U256) -> Result<BigDecimal> {
     let result = BigDecimal::from_str(&value.to_string())?;
     Ok(result)
 }
+
+pub fn uint_to_big_decimal(value: &ethabi::Uint) -> Result<BigDecimal> {
+    let result = BigDecimal::from_str(&value.to_string())?;
+    Ok(result)
+}
+
+// Same format as `accessList` in eth json rpc
+fn access_list_to_json(access_list: &[AccessListItem]) -> Result<String> {
+    let items = access_list
+        .iter()
+        .map(|item| {
+            let storage_keys = item
+                .storage_keys
+                .iter()
+                .map(|key| hex(key.as_slice()))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(serde_json::json!({
+                "address": hex(&item.address)?,
+                "storageKeys": storage_keys,
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(serde_json::to_string(&items)?)
+}
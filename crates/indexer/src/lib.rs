@@ -1,10 +1,20 @@
+pub mod accounts;
+pub mod anomaly;
+pub mod chain_metadata;
 pub mod config;
 pub mod cpu_count;
+pub mod erc_events;
+pub mod gaps;
+pub mod gasless;
 pub mod helper;
 pub mod indexer;
 pub mod insert_l2_block;
+pub mod migration;
+pub mod orphan;
 pub mod pool;
 pub mod runner;
+pub mod signature;
 pub mod types;
+pub mod verify;
 
 pub use indexer::Web3Indexer;
@@ -1,11 +1,97 @@
-use gw_web3_indexer::{config::load_indexer_config, runner::Runner};
+use std::path::PathBuf;
 
-use anyhow::Result;
+use gw_web3_indexer::{
+    chain_metadata::load_chain,
+    config::{indexer_config, set_config_path, DEFAULT_CONFIG_PATH},
+    cpu_count::CPU_COUNT,
+    migration::{check_schema, run_migrations},
+    pool::POOL,
+    runner::{run_status, Runner},
+};
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
 use sentry_log::LogFilter;
 
+#[derive(Parser)]
+#[clap(
+    name = "gw-web3-indexer",
+    version,
+    about = "Index godwoken blocks into the web3 database"
+)]
+struct Cli {
+    /// Path of the configuration file
+    #[clap(long, global = true, default_value = DEFAULT_CONFIG_PATH)]
+    config: PathBuf,
+
+    /// Defaults to sync
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Sync new blocks from godwoken, handling reorgs
+    Sync,
+    /// Re-index blocks already in the database
+    Update {
+        /// First block to update, defaults to 0
+        #[clap(long)]
+        from: Option<u64>,
+        /// Last block to update, defaults to the local tip
+        #[clap(long)]
+        to: Option<u64>,
+        /// Number of cpu cores used to convert rows, defaults to half of local cores
+        #[clap(long)]
+        threads: Option<usize>,
+    },
+    /// Check transaction signatures and record mismatches in anomalies
+    Audit {
+        /// First block to audit, defaults to 0
+        #[clap(long)]
+        from: Option<u64>,
+        /// Last block to audit, defaults to the local tip
+        #[clap(long)]
+        to: Option<u64>,
+    },
+    /// Compare indexed blocks, transactions and logs with godwoken, mismatches are printed as JSON lines
+    Verify {
+        /// First block to verify, defaults to 0
+        #[clap(long)]
+        from: Option<u64>,
+        /// Last block to verify, defaults to the local tip
+        #[clap(long)]
+        to: Option<u64>,
+        /// Rewrite the blocks which don't match godwoken
+        #[clap(long)]
+        fix: bool,
+    },
+    /// Delete all indexed blocks above a block
+    Rollback {
+        /// The block to keep as the new local tip
+        #[clap(long)]
+        to: u64,
+    },
+    /// Print the indexing progress
+    Status,
+    /// Apply pending database migrations
+    Migrate,
+    /// Print the effective configuration, secrets are redacted
+    Config,
+}
+
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Sync);
+    validate_command(&command)?;
+
     init_log();
-    let indexer_config = load_indexer_config("./indexer-config.toml")?;
+    set_config_path(&cli.config)?;
+    let indexer_config = indexer_config()?;
+    if let Command::Config = command {
+        println!("{}", indexer_config);
+        return Ok(());
+    }
 
     let sentry_environment = indexer_config.sentry_environment.clone().map(|e| e.into());
     let _guard = match &indexer_config.sentry_dsn {
@@ -20,31 +106,69 @@ fn main() -> Result<()> {
         None => sentry::init(()),
     };
 
-    let mut runner = Runner::new(indexer_config)?;
-
-    let command_name = std::env::args().nth(1);
-
-    // `cargo run` -> run sync mode
-    // `cargo run update <optional start number> <optional end number>` -> run update mode
-    if let Some(name) = command_name {
-        if name == "update" {
-            let start_block_number = std::env::args()
-                .nth(2)
-                .map(|num| num.parse::<u64>().unwrap());
-            let end_block_number = std::env::args()
-                .nth(3)
-                .map(|num| num.parse::<u64>().unwrap());
-            smol::block_on(runner.run_update(start_block_number, end_block_number))?;
-        } else {
-            smol::block_on(runner.run())?;
+    if let Command::Migrate = command {
+        smol::block_on(run_migrations(&POOL))?;
+        log::info!("Database migrations are applied");
+        return Ok(());
+    }
+    // an upgraded deployment applies the migrations of the new indexer by itself
+    if let Command::Sync = command {
+        smol::block_on(run_migrations(&POOL))?;
+    }
+    smol::block_on(check_schema(&POOL))?;
+    // reports the database even if godwoken is unreachable
+    if let Command::Status = command {
+        return smol::block_on(run_status(indexer_config.godwoken_rpc_url.as_str()));
+    }
+    let chain = smol::block_on(load_chain(&POOL, indexer_config))?;
+
+    let mut runner = Runner::new(indexer_config.clone(), chain)?;
+
+    match command {
+        Command::Sync => smol::block_on(runner.run())?,
+        Command::Update { from, to, threads } => {
+            if let Some(threads) = threads {
+                CPU_COUNT
+                    .set(threads)
+                    .map_err(|_| anyhow!("cpu count is already set"))?;
+            }
+            smol::block_on(runner.run_update(from, to))?
         }
-    } else {
-        smol::block_on(runner.run())?;
+        Command::Audit { from, to } => smol::block_on(runner.run_audit(from, to))?,
+        Command::Verify { from, to, fix } => smol::block_on(runner.run_verify(from, to, fix))?,
+        Command::Rollback { to } => smol::block_on(runner.run_rollback(to))?,
+        Command::Migrate | Command::Config => {
+            unreachable!("runs before the schema check")
+        }
+        Command::Status => unreachable!("runs before loading the chain spec"),
     }
 
     Ok(())
 }
 
+fn validate_command(command: &Command) -> Result<()> {
+    match command {
+        Command::Update {
+            from: Some(from),
+            to: Some(to),
+            ..
+        }
+        | Command::Audit {
+            from: Some(from),
+            to: Some(to),
+        }
+        | Command::Verify {
+            from: Some(from),
+            to: Some(to),
+            ..
+        } if from > to => Err(anyhow!("--from {} is larger than --to {}", from, to)),
+        Command::Update {
+            threads: Some(0), ..
+        } => Err(anyhow!("--threads must be greater than 0")),
+        _ => Ok(()),
+    }
+}
+
 fn init_log() {
     let logger = env_logger::builder()
         .parse_env(env_logger::Env::default().default_filter_or("info"))
@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use sqlx::{migrate::Migrator, Executor, PgPool};
+
+// SQL migrations in crates/indexer/migrations, applied versions are tracked in `_sqlx_migrations`
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// Version of the migration creating the schema of the knex migrations
+const BASELINE_VERSION: i64 = 20221229081526;
+
+// knex migrations in packages/api-server/migrations whose schema is created by each migration,
+// recorded in `knex_migrations` once it is applied so that `make migrate` skips them
+const KNEX_MIGRATIONS: &[(i64, &[&str])] = &[
+    (
+        BASELINE_VERSION,
+        &[
+            "20220512033018_refactor_tables.ts",
+            "20220530035042_fix_transactions_contract_address.ts",
+            "20220701114448_remove_foreign_keys.ts",
+            "20221018014620_fix_log_index.ts",
+            "20221102080006_add_chain_id_to_transactions.ts",
+            "20221110083012_create_orphaned_tables.ts",
+            "20221115021530_add_status_to_blocks.ts",
+            "20221121064210_create_accounts.ts",
+            "20221124031845_add_address_to_accounts.ts",
+            "20221128092413_create_token_transfers.ts",
+            "20221201075326_create_erc_events.ts",
+            "20221205024751_create_withdrawals.ts",
+            "20221208061157_add_status_code_to_transactions.ts",
+            "20221212034622_add_logs_bloom.ts",
+            "20221215083540_add_typed_transaction_fields.ts",
+            "20221219042318_create_anomalies.ts",
+            "20221222071935_create_gw_transactions.ts",
+            "20221226053047_create_gasless_transactions.ts",
+        ],
+    ),
+    (20221230021458, &["20221230021458_create_chain_metadata.ts"]),
+    (
+        20230104032611,
+        &["20230104032611_add_revert_data_to_transactions.ts"],
+    ),
+    (
+        20230106021734,
+        &["20230106021734_create_chain_eoa_locks.ts"],
+    ),
+];
+
+// Apply pending migrations, databases already migrated by knex are adopted by the baseline.
+// The baseline only creates missing tables, so a knex database must already have every column
+// of the baseline in its tables, i.e. be migrated by the api-server of the same version.
+pub async fn run_migrations(pool: &PgPool) -> Result<()> {
+    if !baseline_applied(pool).await? {
+        let missing = missing_baseline_columns(pool).await?;
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "database schema lacks columns of the knex migrations: {}, run `make migrate` with the api-server of this version first",
+                missing.join(", ")
+            ));
+        }
+    }
+    MIGRATOR.run(pool).await?;
+    record_knex_migrations(pool).await?;
+    Ok(())
+}
+
+// Record the knex migrations of the applied migrations in `knex_migrations`, created in the
+// layout of knex if it doesn't exist, so that knex doesn't create the same tables again.
+async fn record_knex_migrations(pool: &PgPool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS knex_migrations (
+            id serial PRIMARY KEY, name varchar(255), batch integer, migration_time timestamptz
+        )",
+    )
+    .execute(&mut tx)
+    .await?;
+    let applied: Vec<(i64,)> = sqlx::query_as("SELECT version FROM _sqlx_migrations WHERE success")
+        .fetch_all(&mut tx)
+        .await?;
+    let recorded: Vec<(String,)> = sqlx::query_as("SELECT name FROM knex_migrations")
+        .fetch_all(&mut tx)
+        .await?;
+
+    // recorded as one batch, like a single `knex migrate:latest`
+    let (batch,): (i32,) =
+        sqlx::query_as("SELECT coalesce(max(batch), 0) + 1 FROM knex_migrations")
+            .fetch_one(&mut tx)
+            .await?;
+
+    let names = KNEX_MIGRATIONS
+        .iter()
+        .filter(|(version, _)| applied.iter().any(|(v,)| v == version))
+        .flat_map(|(_, names)| names.iter())
+        .filter(|name| !recorded.iter().any(|(n,)| n == *name));
+    for name in names {
+        sqlx::query(
+            "INSERT INTO knex_migrations (name, batch, migration_time) VALUES ($1, $2, now())",
+        )
+        .bind(*name)
+        .bind(batch)
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn baseline_applied(pool: &PgPool) -> Result<bool> {
+    let (initialized,): (bool,) =
+        sqlx::query_as("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+    if !initialized {
+        return Ok(false);
+    }
+    let (applied,): (bool,) = sqlx::query_as(
+        "SELECT EXISTS (SELECT 1 FROM _sqlx_migrations WHERE version = $1 AND success)",
+    )
+    .bind(BASELINE_VERSION)
+    .fetch_one(pool)
+    .await?;
+    Ok(applied)
+}
+
+// Columns of the baseline missing in the existing tables, as `table.column`. The baseline is
+// created in a scratch schema of a rolled back transaction and compared in information_schema.
+async fn missing_baseline_columns(pool: &PgPool) -> Result<Vec<String>> {
+    let baseline = MIGRATOR
+        .iter()
+        .find(|m| m.version == BASELINE_VERSION)
+        .ok_or_else(|| anyhow!("baseline migration {} not found", BASELINE_VERSION))?;
+
+    let mut tx = pool.begin().await?;
+    let (schema,): (String,) = sqlx::query_as("SELECT current_schema()")
+        .fetch_one(&mut tx)
+        .await?;
+    sqlx::query("CREATE SCHEMA baseline_check")
+        .execute(&mut tx)
+        .await?;
+    sqlx::query("SET LOCAL search_path TO baseline_check")
+        .execute(&mut tx)
+        .await?;
+    (&mut tx).execute(baseline.sql.as_ref()).await?;
+    let missing: Vec<(String, String)> = sqlx::query_as(
+        "SELECT b.table_name::text, b.column_name::text FROM information_schema.columns b
+        JOIN information_schema.tables t ON t.table_schema = $1 AND t.table_name = b.table_name
+        WHERE b.table_schema = 'baseline_check' AND NOT EXISTS (
+            SELECT 1 FROM information_schema.columns c
+            WHERE c.table_schema = $1 AND c.table_name = b.table_name AND c.column_name = b.column_name
+        ) ORDER BY b.table_name, b.ordinal_position",
+    )
+    .bind(&schema)
+    .fetch_all(&mut tx)
+    .await?;
+    tx.rollback().await?;
+
+    Ok(missing
+        .into_iter()
+        .map(|(table, column)| format!("{}.{}", table, column))
+        .collect())
+}
+
+// Refuse to index into a database whose schema doesn't match the migrations of this indexer.
+pub async fn check_schema(pool: &PgPool) -> Result<()> {
+    let (initialized,): (bool,) =
+        sqlx::query_as("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+    if !initialized {
+        return Err(anyhow!(
+            "database schema is not initialized, run `gw-web3-indexer migrate` first"
+        ));
+    }
+
+    let applied: Vec<(i64, bool, Vec<u8>)> =
+        sqlx::query_as("SELECT version, success, checksum FROM _sqlx_migrations ORDER BY version")
+            .fetch_all(pool)
+            .await?;
+    let known: HashMap<i64, &[u8]> = MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| (m.version, &*m.checksum))
+        .collect();
+
+    for (version, success, checksum) in &applied {
+        if !success {
+            return Err(anyhow!(
+                "migration {} was interrupted, fix the database and run `gw-web3-indexer migrate`",
+                version
+            ));
+        }
+        match known.get(version) {
+            None => {
+                return Err(anyhow!(
+                    "database schema has migration {} unknown to this indexer, upgrade the indexer",
+                    version
+                ))
+            }
+            Some(known_checksum) if *known_checksum != checksum.as_slice() => {
+                return Err(anyhow!(
+                    "migration {} applied to the database differs from the one of this indexer",
+                    version
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    let mut pending: Vec<_> = known
+        .keys()
+        .filter(|version| !applied.iter().any(|(v, _, _)| v == *version))
+        .collect();
+    if !pending.is_empty() {
+        pending.sort();
+        return Err(anyhow!(
+            "database schema is outdated, migrations {:?} are pending, run `gw-web3-indexer migrate` first",
+            pending
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::test_database;
+
+    // Schema of a knex database migrated up to 20221018014620_fix_log_index, i.e. before
+    // 20221102080006_add_chain_id_to_transactions
+    const PRE_CHAIN_ID_SCHEMA: &str = "
+        ALTER TABLE blocks DROP COLUMN status, DROP COLUMN logs_bloom;
+        ALTER TABLE transactions DROP COLUMN chain_id, DROP COLUMN status_code,
+            DROP COLUMN failure_reason, DROP COLUMN logs_bloom, DROP COLUMN type,
+            DROP COLUMN access_list, DROP COLUMN max_priority_fee_per_gas,
+            DROP COLUMN max_fee_per_gas;
+        DROP TABLE orphaned_blocks, orphaned_transactions, orphaned_logs, accounts,
+            token_transfers, orphaned_token_transfers, erc_transfers, erc_approvals, withdrawals,
+            orphaned_withdrawals, anomalies, gw_transactions, orphaned_gw_transactions,
+            gasless_transactions, orphaned_gasless_transactions;
+    ";
+
+    // Create the schema of the baseline as knex does, without `_sqlx_migrations`
+    async fn create_knex_schema(pool: &PgPool) {
+        let baseline = MIGRATOR
+            .iter()
+            .find(|m| m.version == BASELINE_VERSION)
+            .unwrap();
+        pool.execute(baseline.sql.as_ref()).await.unwrap();
+    }
+
+    async fn knex_migrations(pool: &PgPool) -> Vec<(String, i32)> {
+        sqlx::query_as("SELECT name, batch FROM knex_migrations ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    fn knex_migration_names() -> Vec<&'static str> {
+        KNEX_MIGRATIONS
+            .iter()
+            .flat_map(|(_, names)| names.iter().copied())
+            .collect()
+    }
+
+    async fn sqlx_migrations_exists(pool: &PgPool) -> bool {
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+                .fetch_one(pool)
+                .await
+                .unwrap();
+        exists
+    }
+
+    #[test]
+    fn test_migrate_fresh_database() {
+        smol::block_on(async {
+            let pool = match test_database("test_migrate_fresh_database").await {
+                Some(pool) => pool,
+                None => return,
+            };
+            assert!(missing_baseline_columns(&pool).await.unwrap().is_empty());
+            run_migrations(&pool).await.unwrap();
+            assert!(baseline_applied(&pool).await.unwrap());
+            assert!(missing_baseline_columns(&pool).await.unwrap().is_empty());
+            check_schema(&pool).await.unwrap();
+            let recorded = knex_migrations(&pool).await;
+            assert_eq!(
+                recorded.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>(),
+                knex_migration_names()
+            );
+            assert!(recorded.iter().all(|(_, batch)| *batch == 1));
+            // idempotent
+            run_migrations(&pool).await.unwrap();
+            assert_eq!(knex_migrations(&pool).await, recorded);
+        });
+    }
+
+    #[test]
+    fn test_adopt_knex_database() {
+        smol::block_on(async {
+            let pool = match test_database("test_adopt_knex_database").await {
+                Some(pool) => pool,
+                None => return,
+            };
+            create_knex_schema(&pool).await;
+            pool.execute(
+                "CREATE TABLE knex_migrations (
+                    id serial PRIMARY KEY, name varchar(255), batch integer, migration_time timestamptz
+                )",
+            )
+            .await
+            .unwrap();
+            for name in KNEX_MIGRATIONS[0].1 {
+                sqlx::query("INSERT INTO knex_migrations (name, batch) VALUES ($1, 1)")
+                    .bind(*name)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+            assert!(!baseline_applied(&pool).await.unwrap());
+            run_migrations(&pool).await.unwrap();
+            check_schema(&pool).await.unwrap();
+
+            // migrations after the baseline are recorded in a new batch
+            let recorded = knex_migrations(&pool).await;
+            assert_eq!(
+                recorded.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>(),
+                knex_migration_names()
+            );
+            let baseline_len = KNEX_MIGRATIONS[0].1.len();
+            assert!(recorded[..baseline_len]
+                .iter()
+                .all(|(_, batch)| *batch == 1));
+            assert!(recorded[baseline_len..]
+                .iter()
+                .all(|(_, batch)| *batch == 2));
+        });
+    }
+
+    #[test]
+    fn test_knex_migrations_listed() {
+        let dir = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../packages/api-server/migrations"
+        );
+        let mut files = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, knex_migration_names());
+        for (version, _) in KNEX_MIGRATIONS {
+            assert!(MIGRATOR.iter().any(|m| m.version == *version));
+        }
+    }
+
+    #[test]
+    fn test_refuse_pre_chain_id_knex_database() {
+        smol::block_on(async {
+            let pool = match test_database("test_refuse_pre_chain_id_knex_database").await {
+                Some(pool) => pool,
+                None => return,
+            };
+            create_knex_schema(&pool).await;
+            pool.execute(PRE_CHAIN_ID_SCHEMA).await.unwrap();
+
+            let missing = missing_baseline_columns(&pool).await.unwrap();
+            assert_eq!(
+                missing,
+                vec![
+                    "blocks.status",
+                    "blocks.logs_bloom",
+                    "transactions.chain_id",
+                    "transactions.status_code",
+                    "transactions.failure_reason",
+                    "transactions.logs_bloom",
+                    "transactions.type",
+                    "transactions.access_list",
+                    "transactions.max_priority_fee_per_gas",
+                    "transactions.max_fee_per_gas",
+                ]
+            );
+
+            let err = run_migrations(&pool).await.unwrap_err();
+            assert!(err.to_string().contains("transactions.chain_id"));
+            // nothing is applied, and the scratch schema is rolled back
+            assert!(!sqlx_migrations_exists(&pool).await);
+            let (scratch,): (bool,) =
+                sqlx::query_as("SELECT to_regnamespace('baseline_check') IS NOT NULL")
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+            assert!(!scratch);
+            assert!(check_schema(&pool).await.is_err());
+        });
+    }
+}
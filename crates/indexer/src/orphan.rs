@@ -0,0 +1,130 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool, Postgres,
+};
+
+// Copy rows of `block_number` in blocks, transactions, logs, token_transfers, withdrawals,
+// gw_transactions and gasless_transactions into the orphaned tables,
+// `replaced_by` is the hash of the canonical block at the same height.
+pub async fn archive_orphaned_block(
+    block_number: u64,
+    replaced_by: Option<&[u8]>,
+    orphaned_at: DateTime<Utc>,
+    pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+) -> Result<()> {
+    let number = Decimal::from(block_number);
+
+    sqlx::query(
+        "INSERT INTO orphaned_blocks (number, hash, parent_hash, gas_limit, gas_used, timestamp, miner, size, logs_bloom, replaced_by, orphaned_at)
+        SELECT number, hash, parent_hash, gas_limit, gas_used, timestamp, miner, size, logs_bloom, $2, $3 FROM blocks WHERE number = $1"
+    )
+    .bind(number)
+    .bind(replaced_by)
+    .bind(orphaned_at)
+    .execute(&mut *pg_tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO orphaned_transactions (hash, eth_tx_hash, block_number, block_hash, transaction_index, from_address, to_address, value, nonce, gas_limit, gas_price, input, v, r, s, cumulative_gas_used, gas_used, contract_address, exit_code, chain_id, status_code, failure_reason, revert_data, logs_bloom, type, access_list, max_priority_fee_per_gas, max_fee_per_gas, replaced_by, orphaned_at)
+        SELECT hash, eth_tx_hash, block_number, block_hash, transaction_index, from_address, to_address, value, nonce, gas_limit, gas_price, input, v, r, s, cumulative_gas_used, gas_used, contract_address, exit_code, chain_id, status_code, failure_reason, revert_data, logs_bloom, type, access_list, max_priority_fee_per_gas, max_fee_per_gas, $2, $3 FROM transactions WHERE block_number = $1"
+    )
+    .bind(number)
+    .bind(replaced_by)
+    .bind(orphaned_at)
+    .execute(&mut *pg_tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO orphaned_logs (transaction_hash, transaction_index, block_number, block_hash, address, data, log_index, topics, replaced_by, orphaned_at)
+        SELECT transaction_hash, transaction_index, block_number, block_hash, address, data, log_index, topics, $2, $3 FROM logs WHERE block_number = $1"
+    )
+    .bind(number)
+    .bind(replaced_by)
+    .bind(orphaned_at)
+    .execute(&mut *pg_tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO orphaned_token_transfers (transaction_hash, transaction_index, block_number, block_hash, transfer_index, sudt_id, from_registry_id, from_address, to_registry_id, to_address, amount, kind, replaced_by, orphaned_at)
+        SELECT transaction_hash, transaction_index, block_number, block_hash, transfer_index, sudt_id, from_registry_id, from_address, to_registry_id, to_address, amount, kind, $2, $3 FROM token_transfers WHERE block_number = $1"
+    )
+    .bind(number)
+    .bind(replaced_by)
+    .bind(orphaned_at)
+    .execute(&mut *pg_tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO orphaned_withdrawals (block_number, block_hash, withdrawal_index, account_script_hash, registry_id, registry_address, capacity, sudt_script_hash, amount, owner_lock_hash, nonce, fee, chain_id, replaced_by, orphaned_at)
+        SELECT block_number, block_hash, withdrawal_index, account_script_hash, registry_id, registry_address, capacity, sudt_script_hash, amount, owner_lock_hash, nonce, fee, chain_id, $2, $3 FROM withdrawals WHERE block_number = $1"
+    )
+    .bind(number)
+    .bind(replaced_by)
+    .bind(orphaned_at)
+    .execute(&mut *pg_tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO orphaned_gw_transactions (hash, block_number, block_hash, l2_tx_index, from_id, to_id, nonce, args, from_lock_hash, eoa_type, exit_code, replaced_by, orphaned_at)
+        SELECT hash, block_number, block_hash, l2_tx_index, from_id, to_id, nonce, args, from_lock_hash, eoa_type, exit_code, $2, $3 FROM gw_transactions WHERE block_number = $1"
+    )
+    .bind(number)
+    .bind(replaced_by)
+    .bind(orphaned_at)
+    .execute(&mut *pg_tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO orphaned_gasless_transactions (transaction_hash, block_number, block_hash, transaction_index, sender, entrypoint, call_contract, call_data, call_gas_limit, verification_gas_limit, max_fee_per_gas, max_priority_fee_per_gas, paymaster, paymaster_and_data, replaced_by, orphaned_at)
+        SELECT transaction_hash, block_number, block_hash, transaction_index, sender, entrypoint, call_contract, call_data, call_gas_limit, verification_gas_limit, max_fee_per_gas, max_priority_fee_per_gas, paymaster, paymaster_and_data, $2, $3 FROM gasless_transactions WHERE block_number = $1"
+    )
+    .bind(number)
+    .bind(replaced_by)
+    .bind(orphaned_at)
+    .execute(&mut *pg_tx)
+    .await?;
+
+    Ok(())
+}
+
+// Delete orphaned rows archived more than `retention` ago, returns the number of deleted blocks.
+pub async fn prune_orphaned_blocks(pool: &PgPool, retention: Duration) -> Result<u64> {
+    let before: DateTime<Utc> = (SystemTime::now() - retention).into();
+
+    let mut pg_tx = pool.begin().await?;
+    sqlx::query("DELETE FROM orphaned_gasless_transactions WHERE orphaned_at < $1")
+        .bind(before)
+        .execute(&mut pg_tx)
+        .await?;
+    sqlx::query("DELETE FROM orphaned_gw_transactions WHERE orphaned_at < $1")
+        .bind(before)
+        .execute(&mut pg_tx)
+        .await?;
+    sqlx::query("DELETE FROM orphaned_withdrawals WHERE orphaned_at < $1")
+        .bind(before)
+        .execute(&mut pg_tx)
+        .await?;
+    sqlx::query("DELETE FROM orphaned_token_transfers WHERE orphaned_at < $1")
+        .bind(before)
+        .execute(&mut pg_tx)
+        .await?;
+    sqlx::query("DELETE FROM orphaned_logs WHERE orphaned_at < $1")
+        .bind(before)
+        .execute(&mut pg_tx)
+        .await?;
+    sqlx::query("DELETE FROM orphaned_transactions WHERE orphaned_at < $1")
+        .bind(before)
+        .execute(&mut pg_tx)
+        .await?;
+    let result = sqlx::query("DELETE FROM orphaned_blocks WHERE orphaned_at < $1")
+        .bind(before)
+        .execute(&mut pg_tx)
+        .await?;
+    pg_tx.commit().await?;
+
+    Ok(result.rows_affected())
+}
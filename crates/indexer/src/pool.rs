@@ -5,29 +5,35 @@ use sqlx::{
     ConnectOptions, PgPool,
 };
 
-use crate::config::load_indexer_config;
+use crate::config::indexer_config;
 
 lazy_static::lazy_static! {
     pub static ref POOL: PgPool = {
-        let indexer_config = load_indexer_config("./indexer-config.toml").unwrap();
+        let indexer_config = indexer_config().expect("load indexer config");
 
-        let mut opts: PgConnectOptions = indexer_config.pg_url.parse().expect("pg url parse error");
+        let mut opts: PgConnectOptions = indexer_config.pg_url.parse().expect("pg url is validated");
         opts.log_statements(log::LevelFilter::Debug)
-            .log_slow_statements(log::LevelFilter::Warn, Duration::from_secs(5));
+            .log_slow_statements(
+                log::LevelFilter::Warn,
+                Duration::from_secs(indexer_config.pool.slow_statement_secs),
+            );
         PgPoolOptions::new()
-            .max_connections(5)
+            .max_connections(indexer_config.pool.max_connections)
             .connect_lazy_with(opts)
     };
 
-    // Adapt slow query duration to 30s, and enlarge max connections
+    // Slow query duration and max connections of the update command
     pub static ref POOL_FOR_UPDATE: PgPool = {
-        let indexer_config = load_indexer_config("./indexer-config.toml").unwrap();
+        let indexer_config = indexer_config().expect("load indexer config");
 
-        let mut opts: PgConnectOptions = indexer_config.pg_url.parse().expect("pg url parse error");
+        let mut opts: PgConnectOptions = indexer_config.pg_url.parse().expect("pg url is validated");
         opts.log_statements(log::LevelFilter::Debug)
-            .log_slow_statements(log::LevelFilter::Warn, Duration::from_secs(30));
+            .log_slow_statements(
+                log::LevelFilter::Warn,
+                Duration::from_secs(indexer_config.pool.update_slow_statement_secs),
+            );
         PgPoolOptions::new()
-            .max_connections(20)
+            .max_connections(indexer_config.pool.update_max_connections)
             .connect_lazy_with(opts)
     };
 }
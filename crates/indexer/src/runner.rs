@@ -1,41 +1,186 @@
+use std::{
+    fmt,
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
 use ckb_types::prelude::Entity;
+use futures::StreamExt;
 use gw_web3_rpc_client::{
-    convertion::to_l2_block, error::RpcClientError, godwoken_rpc_client::GodwokenRpcClient,
+    convertion::to_l2_block, error::RpcClientError, godwoken_async_client::GodwokenAsyncClient,
 };
 use rust_decimal::{prelude::ToPrimitive, Decimal};
+use smol::{channel::Receiver, lock::Mutex, Task};
+use sqlx::Postgres;
 
-use crate::{config::IndexerConfig, helper::hex, pool::POOL, Web3Indexer};
+use crate::{
+    chain_metadata::{is_connection_error, verify_chain, Chain},
+    config::{ChainSpec, IndexerConfig},
+    gaps::{scan_gaps, Backfill, Gaps},
+    helper::hex,
+    insert_l2_block::BLOCK_DERIVED_TABLES,
+    orphan::{archive_orphaned_block, prune_orphaned_blocks},
+    pool::POOL,
+    types::{Block as Web3Block, BlockStatus, BlockWithTransactions},
+    verify::verify_block,
+    Web3Indexer,
+};
 use anyhow::{anyhow, Result};
 
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug)]
+pub struct ReorgEvent {
+    /// Number of local blocks rolled back
+    pub depth: u64,
+    pub common_ancestor: Option<u64>,
+    pub old_tip_number: u64,
+    pub old_tip_hash: ckb_types::H256,
+    /// The block on godwoken which triggered this reorg
+    pub new_block_number: u64,
+    pub new_block_hash: ckb_types::H256,
+}
+
+impl Display for ReorgEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Reorg {{ ")?;
+        write!(f, "depth: {}, ", self.depth)?;
+        if let Some(n) = self.common_ancestor {
+            write!(f, "common_ancestor: {}, ", n)?;
+        } else {
+            write!(f, "common_ancestor: null, ")?;
+        }
+        write!(f, "old_tip_number: {}, ", self.old_tip_number)?;
+        write!(f, "old_tip_hash: 0x{}, ", self.old_tip_hash)?;
+        write!(f, "new_block_number: {}, ", self.new_block_number)?;
+        write!(f, "new_block_hash: 0x{}", self.new_block_hash)?;
+        write!(f, " }}")
+    }
+}
+
+// Blocks prefetched by the last `insert`, the next call continues from the same stream
+struct Prefetch {
+    _task: Task<()>,
+    blocks: Receiver<Result<BlockWithTransactions>>,
+    next_block_number: u64,
+}
+
 pub struct Runner {
-    indexer: Web3Indexer,
+    indexer: Arc<Web3Indexer>,
     local_tip: Option<u64>,
-    godwoken_rpc_client: GodwokenRpcClient,
+    godwoken_async_client: GodwokenAsyncClient,
+    block_prefetch_window: usize,
+    block_fetch_concurrency: usize,
+    prefetch: Option<Prefetch>,
+    archive_orphaned_blocks: bool,
+    orphan_retention: Option<Duration>,
+    confirmation_depth: u64,
+    // Max number of blocks deleted in one database transaction by the rollback command
+    rollback_batch_size: u64,
+    poll_interval: Duration,
+    update_retry_interval: Duration,
+    finality_check_interval: Duration,
+    orphan_prune_interval: Duration,
+    gap_scan_interval: Duration,
+    // First block of the next gap scan, shared with the backfill and held while rolling back
+    gap_scan_from: Arc<Mutex<u64>>,
+    // Source and chain spec loaded without godwoken, None once godwoken has verified them
+    unverified_chain: Option<(&'static str, ChainSpec)>,
 }
 
 impl Runner {
-    pub fn new(config: IndexerConfig) -> Result<Runner> {
-        let indexer = Web3Indexer::new(
-            config.l2_sudt_type_script_hash,
-            config.polyjuice_type_script_hash,
-            config.rollup_type_hash,
-            config.eth_account_lock_hash,
+    pub fn new(config: IndexerConfig, chain: Chain) -> Result<Runner> {
+        let Chain {
+            chain_spec,
+            eoa_locks,
+            source,
+            verified,
+        } = chain;
+        let unverified_chain = if verified {
+            None
+        } else {
+            Some((source, chain_spec.clone()))
+        };
+        let indexer = Arc::new(Web3Indexer::new(
+            chain_spec.l2_sudt_type_script_hash,
+            chain_spec.polyjuice_type_script_hash,
+            chain_spec.rollup_type_hash,
+            chain_spec.eth_account_lock_hash,
+            eoa_locks,
             config.godwoken_rpc_url.as_str(),
-        );
-        let godwoken_rpc_client = GodwokenRpcClient::new(config.godwoken_rpc_url.as_str());
+            config.account_cache_size,
+            config.persist_accounts,
+            config.verify_signatures,
+            config.gasless_entrypoint_address,
+            config.batch.clone(),
+            config.retry.clone(),
+        )?);
+        let godwoken_async_client =
+            GodwokenAsyncClient::with_url(config.godwoken_rpc_url.as_str())?;
         let runner = Runner {
             indexer,
             local_tip: None,
-            godwoken_rpc_client,
+            godwoken_async_client,
+            block_prefetch_window: config.block_prefetch_window,
+            block_fetch_concurrency: config.block_fetch_concurrency,
+            prefetch: None,
+            archive_orphaned_blocks: config.archive_orphaned_blocks,
+            orphan_retention: config
+                .orphan_retention_days
+                .map(|days| Duration::from_secs(days * SECS_PER_DAY)),
+            confirmation_depth: config.confirmation_depth,
+            rollback_batch_size: config.batch.rollback_blocks,
+            poll_interval: Duration::from_secs(config.interval.poll_secs),
+            update_retry_interval: Duration::from_secs(config.interval.update_retry_secs),
+            finality_check_interval: Duration::from_secs(config.interval.finality_check_secs),
+            orphan_prune_interval: Duration::from_secs(config.interval.orphan_prune_secs),
+            gap_scan_interval: Duration::from_secs(config.interval.gap_scan_secs),
+            gap_scan_from: Arc::new(Mutex::new(0)),
+            unverified_chain,
         };
         Ok(runner)
     }
 
+    // Verify the chain spec loaded without godwoken, false if godwoken can't be connected yet.
+    async fn try_verify_chain(&mut self) -> Result<bool> {
+        let (source, chain_spec) = match &self.unverified_chain {
+            Some(chain) => chain,
+            None => return Ok(true),
+        };
+        match verify_chain(
+            &POOL,
+            &self.godwoken_async_client,
+            Some((*source, chain_spec)),
+        )
+        .await
+        {
+            Ok(_) => {
+                log::info!("Chain spec from {} is verified by godwoken", source);
+                self.unverified_chain = None;
+                Ok(true)
+            }
+            Err(err) if is_connection_error(&err) => {
+                log::warn!("Verify chain spec failed: {}", err);
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    // Wait until godwoken is reachable and matches the chain spec, blocks are only written after it.
+    async fn wait_for_verified_chain(&mut self) -> Result<()> {
+        while !self.try_verify_chain().await? {
+            smol::Timer::after(self.poll_interval).await;
+        }
+        Ok(())
+    }
+
     // None means no local blocks
     pub async fn tip(&self) -> Result<Option<u64>> {
         let tip = match self.local_tip {
             Some(t) => Some(t),
-            None => self.get_db_tip_number().await?,
+            None => get_db_tip_number().await?,
         };
         Ok(tip)
     }
@@ -43,7 +188,7 @@ impl Runner {
     pub async fn bump_tip(&mut self) -> Result<()> {
         match self.local_tip {
             None => {
-                self.local_tip = if let Some(n) = self.get_db_tip_number().await? {
+                self.local_tip = if let Some(n) = get_db_tip_number().await? {
                     Some(n)
                 } else {
                     Some(0)
@@ -69,16 +214,6 @@ impl Runner {
         Ok(())
     }
 
-    async fn get_db_tip_number(&self) -> Result<Option<u64>> {
-        let row: Option<(Decimal,)> =
-            sqlx::query_as("select number from blocks order by number desc limit 1;")
-                .fetch_optional(&*POOL)
-                .await?;
-
-        let num = row.and_then(|(n,)| n.to_u64());
-        Ok(num)
-    }
-
     async fn get_db_block_hash(&self, block_number: u64) -> Result<Option<ckb_types::H256>> {
         let row: Option<(Vec<u8>,)> =
             sqlx::query_as("select hash from blocks where number = $1 limit 1;")
@@ -93,23 +228,191 @@ impl Runner {
         Ok(None)
     }
 
-    async fn delete_block(&self, block_number: u64) -> Result<()> {
-        let number = Decimal::from(block_number);
-        let pool = &*POOL;
-        let mut tx = pool.begin().await?;
-        sqlx::query("delete from logs where block_number = $1;")
+    async fn get_db_lowest_unfinalized_number(&self) -> Result<Option<u64>> {
+        let row: Option<(Decimal,)> = sqlx::query_as(
+            "select number from blocks where status = $1 order by number asc limit 1;",
+        )
+        .bind(BlockStatus::Unfinalized.as_str())
+        .fetch_optional(&*POOL)
+        .await?;
+
+        let num = row.and_then(|(n,)| n.to_u64());
+        Ok(num)
+    }
+
+    // Status of the local block `block_number` on godwoken, None if it isn't found.
+    async fn get_chain_block_status(&self, block_number: u64) -> Result<Option<BlockStatus>> {
+        let block_hash = match self.get_db_block_hash(block_number).await? {
+            Some(h) => h,
+            None => return Ok(None),
+        };
+        let block = self.godwoken_async_client.get_block(&block_hash).await?;
+        let status = block.map(|b| BlockStatus::from(b.status));
+        if status == Some(BlockStatus::Reverted) {
+            sqlx::query("update blocks set status = $1 where number = $2 and hash = $3;")
+                .bind(BlockStatus::Reverted.as_str())
+                .bind(Decimal::from(block_number))
+                .bind(block_hash.as_bytes())
+                .execute(&*POOL)
+                .await?;
+        }
+        Ok(status)
+    }
+
+    // Blocks finalize in order, so binary search the highest finalized block among
+    // the unfinalized local blocks, and mark all blocks below it as finalized.
+    async fn update_finalized_blocks(&self) -> Result<()> {
+        let (mut low, mut high) = match (
+            self.get_db_lowest_unfinalized_number().await?,
+            get_db_tip_number().await?,
+        ) {
+            (Some(low), Some(high)) => (low, high),
+            _ => return Ok(()),
+        };
+
+        let mut finalized_number = None;
+        while low <= high {
+            let mid = low + (high - low) / 2;
+            if self.get_chain_block_status(mid).await? == Some(BlockStatus::Finalized) {
+                finalized_number = Some(mid);
+                low = mid + 1;
+            } else if mid == 0 {
+                break;
+            } else {
+                high = mid - 1;
+            }
+        }
+
+        if let Some(number) = finalized_number {
+            let result =
+                sqlx::query("update blocks set status = $1 where number <= $2 and status = $3;")
+                    .bind(BlockStatus::Finalized.as_str())
+                    .bind(Decimal::from(number))
+                    .bind(BlockStatus::Unfinalized.as_str())
+                    .execute(&*POOL)
+                    .await?;
+            log::info!(
+                "Finalized block {}, {} blocks updated",
+                number,
+                result.rows_affected()
+            );
+        }
+        Ok(())
+    }
+
+    // The highest block number to index, None means no limit.
+    async fn get_max_indexable_number(&self) -> Result<Option<u64>> {
+        if self.confirmation_depth == 0 {
+            return Ok(None);
+        }
+        let tip_number = get_chain_tip_number(&self.godwoken_async_client).await?;
+        Ok(tip_number.checked_sub(self.confirmation_depth))
+    }
+
+    // Delete blocks and all rows derived from them whose block number >= `start_block_number`.
+    // Returns the number of deleted rows of each table.
+    async fn delete_blocks_from(
+        &self,
+        start_block_number: u64,
+        pg_tx: &mut sqlx::Transaction<'_, Postgres>,
+    ) -> Result<Vec<(&'static str, u64)>> {
+        let number = Decimal::from(start_block_number);
+        let mut deleted = Vec::with_capacity(BLOCK_DERIVED_TABLES.len() + 1);
+        for table in BLOCK_DERIVED_TABLES {
+            let result = sqlx::query(&format!("delete from {} where block_number >= $1;", table))
+                .bind(number)
+                .execute(&mut *pg_tx)
+                .await?;
+            deleted.push((table, result.rows_affected()));
+        }
+        let result = sqlx::query("delete from blocks where number >= $1;")
             .bind(number)
-            .execute(&mut tx)
+            .execute(&mut *pg_tx)
             .await?;
-        sqlx::query("delete from transactions where block_number = $1;")
-            .bind(number)
-            .execute(&mut tx)
+        deleted.push(("blocks", result.rows_affected()));
+        Ok(deleted)
+    }
+
+    // Walk back from `block_number` until the block hash in db equals the block hash on chain.
+    // `chain_block_hash` is the hash of `block_number` on chain.
+    //
+    // Returns the common ancestor, None means all local blocks are orphaned,
+    // and the canonical block hashes of the orphaned heights.
+    async fn find_common_ancestor(
+        &self,
+        block_number: u64,
+        chain_block_hash: ckb_types::H256,
+    ) -> Result<(Option<u64>, Vec<(u64, ckb_types::H256)>)> {
+        let mut number = block_number;
+        let mut chain_block_hash = chain_block_hash;
+        let mut replaced_hashes = vec![];
+        loop {
+            if self.get_db_block_hash(number).await?.as_ref() == Some(&chain_block_hash) {
+                return Ok((Some(number), replaced_hashes));
+            }
+            replaced_hashes.push((number, chain_block_hash));
+            if number == 0 {
+                return Ok((None, replaced_hashes));
+            }
+            number -= 1;
+            chain_block_hash = self
+                .godwoken_async_client
+                .get_block_hash(number)
+                .await?
+                .ok_or_else(|| anyhow!("block {} not exist!", number))?;
+        }
+    }
+
+    // Rollback all local blocks after the common ancestor of local chain and godwoken,
+    // `new_block` is the block on godwoken whose parent doesn't match the local tip.
+    async fn reorg(
+        &mut self,
+        old_tip_number: u64,
+        old_tip_hash: ckb_types::H256,
+        new_block: &Web3Block,
+    ) -> Result<()> {
+        let new_block_parent_hash = ckb_types::H256::from_slice(new_block.parent_hash.as_slice())?;
+        let (common_ancestor, replaced_hashes) = self
+            .find_common_ancestor(old_tip_number, new_block_parent_hash)
             .await?;
-        sqlx::query("delete from blocks where number = $1;")
-            .bind(number)
-            .execute(&mut tx)
+        let rollback_from = common_ancestor.map(|n| n + 1).unwrap_or(0);
+
+        // backfills wait until the rollback is committed
+        let mut gap_scan_from = self.gap_scan_from.lock().await;
+        let pool = &*POOL;
+        let mut pg_tx = pool.begin().await?;
+        if self.archive_orphaned_blocks {
+            let orphaned_at = SystemTime::now().into();
+            for (number, replaced_by) in replaced_hashes.iter() {
+                archive_orphaned_block(
+                    *number,
+                    Some(replaced_by.as_bytes()),
+                    orphaned_at,
+                    &mut pg_tx,
+                )
+                .await?;
+            }
+        }
+        self.delete_blocks_from(rollback_from, &mut pg_tx).await?;
+        self.indexer
+            .rollback_accounts(rollback_from, &mut pg_tx)
             .await?;
-        tx.commit().await?;
+        pg_tx.commit().await?;
+        self.local_tip = common_ancestor;
+        // blocks above the common ancestor are indexed again, scan them again
+        *gap_scan_from = (*gap_scan_from).min(common_ancestor.unwrap_or(0));
+        drop(gap_scan_from);
+
+        let event = ReorgEvent {
+            depth: old_tip_number + 1 - rollback_from,
+            common_ancestor,
+            old_tip_number,
+            old_tip_hash,
+            new_block_number: new_block.number,
+            new_block_hash: ckb_types::H256::from_slice(new_block.hash.as_slice())?,
+        };
+        log::warn!("{}", event);
+
         Ok(())
     }
 
@@ -117,8 +420,9 @@ impl Runner {
         let start = std::time::Instant::now();
 
         let current_block = self
-            .godwoken_rpc_client
-            .get_block_by_number(current_block_number)?
+            .godwoken_async_client
+            .get_block_by_number(current_block_number)
+            .await?
             .ok_or_else(|| anyhow!("block {} not exist!", current_block_number))?;
 
         let l2_block = to_l2_block(current_block);
@@ -146,8 +450,7 @@ impl Runner {
                 } else {
                     // Sleep and try again, wait for indexer to deal with revert
                     log::info!("block {}'s parent_block_hash: {} not match prev block's hash {}, sleep and try again", current_block_number, hex(l2_block_parent_hash.as_slice())?, hex(prev_block_hash.as_bytes())?);
-                    let sleep_time = std::time::Duration::from_secs(3);
-                    smol::Timer::after(sleep_time).await;
+                    smol::Timer::after(self.update_retry_interval).await;
                 }
             }
         } else {
@@ -167,83 +470,184 @@ impl Runner {
         Ok(false)
     }
 
-    pub async fn insert(&mut self) -> Result<bool> {
-        let start = std::time::Instant::now();
+    // Fetch and convert blocks from `start_block_number` in the background, in order.
+    //
+    // Up to `block_fetch_concurrency` blocks are fetched at the same time, and up to
+    // `block_prefetch_window` converted blocks wait in the channel to be committed.
+    // The stream ends at the first block which doesn't exist yet, after `end_block_number`,
+    // or after the first error. Dropping the returned task stops prefetching.
+    fn prefetch_blocks(
+        &self,
+        start_block_number: u64,
+        end_block_number: Option<u64>,
+    ) -> (Task<()>, Receiver<Result<BlockWithTransactions>>) {
+        let (sender, receiver) = smol::channel::bounded(self.block_prefetch_window);
+        let indexer = Arc::clone(&self.indexer);
+        let concurrency = self.block_fetch_concurrency;
+        let blocks_len = match end_block_number {
+            Some(end) => (end + 1).saturating_sub(start_block_number) as usize,
+            None => usize::MAX,
+        };
+
+        let task = smol::spawn(async move {
+            let mut blocks = futures::stream::iter((start_block_number..).take(blocks_len))
+                .map(move |number| {
+                    let indexer = Arc::clone(&indexer);
+                    async move { indexer.prefetch_l2_block(number).await }
+                })
+                .buffered(concurrency);
 
+            while let Some(result) = blocks.next().await {
+                let (item, is_last) = match result {
+                    Ok(Some(block)) => (Ok(block), false),
+                    // reach the tip of godwoken
+                    Ok(None) => break,
+                    Err(err) => (Err(err), true),
+                };
+                if sender.send(item).await.is_err() || is_last {
+                    break;
+                }
+            }
+        });
+
+        (task, receiver)
+    }
+
+    // Insert at most `block_prefetch_window` blocks, so that the periodic tasks of `run`
+    // aren't starved while catching up. Returns whether any block was inserted or rolled back.
+    pub async fn insert(&mut self) -> Result<bool> {
         let local_tip = self.tip().await?;
-        let current_block_number = match local_tip {
+        let start_block_number = match local_tip {
             None => 0,
             Some(t) => t + 1,
         };
+        let mut prev_block_hash = match local_tip {
+            None => None,
+            Some(t) => self.get_db_block_hash(t).await?,
+        };
 
-        let current_block = self
-            .godwoken_rpc_client
-            .get_block_by_number(current_block_number)?;
+        // Keep prefetching from the last call unless the local tip moved meanwhile,
+        // dropping the prefetch cancels its task
+        let mut prefetch = match self.prefetch.take() {
+            Some(prefetch) if prefetch.next_block_number == start_block_number => prefetch,
+            _ => {
+                let max_block_number = self.get_max_indexable_number().await?;
+                let (task, blocks) = self.prefetch_blocks(start_block_number, max_block_number);
+                Prefetch {
+                    _task: task,
+                    blocks,
+                    next_block_number: start_block_number,
+                }
+            }
+        };
+
+        let mut inserted = false;
+        for _ in 0..self.block_prefetch_window {
+            let prefetched = match prefetch.blocks.recv().await {
+                Ok(prefetched) => prefetched,
+                // the stream reached its end
+                Err(_) => return Ok(inserted),
+            };
+            let start = std::time::Instant::now();
 
-        if let Some(b) = current_block {
-            let l2_block = to_l2_block(b);
-            let l2_block_parent_hash = l2_block.raw().parent_block_hash();
+            let web3_block_with_txs = prefetched?;
+            let current_block_number = web3_block_with_txs.block.number;
+            let current_block_hash = web3_block_with_txs.block.hash;
+            let l2_block_parent_hash = web3_block_with_txs.block.parent_hash;
 
             if current_block_number > 0 {
                 let prev_block_number = current_block_number - 1;
-                let db_prev_block_hash = self.get_db_block_hash(prev_block_number).await?;
-                if let Some(prev_block_hash) = db_prev_block_hash {
-                    // if match, insert a new block
-                    // if not match, delete prev block
-                    if l2_block_parent_hash.as_slice() == prev_block_hash.as_bytes() {
-                        let (txs_len, logs_len) = self.indexer.store_l2_block(l2_block).await?;
-
-                        let duration = start.elapsed();
-                        log::info!(
-                            "Sync block {}, {} txs, {} logs, duration: {:?}",
-                            current_block_number,
-                            txs_len,
-                            logs_len,
-                            duration,
-                        );
-                        self.bump_tip().await?;
-                    } else {
-                        self.delete_block(prev_block_number).await?;
-                        log::info!("Rollback block {}", prev_block_number);
-                        self.revert_tip()?;
+                match &prev_block_hash {
+                    Some(prev_block_hash)
+                        if l2_block_parent_hash.as_slice() == prev_block_hash.as_bytes() => {}
+                    // if not match, rollback to the common ancestor
+                    Some(prev_block_hash) => {
+                        let prev_block_hash = prev_block_hash.clone();
+                        self.reorg(
+                            prev_block_number,
+                            prev_block_hash,
+                            &web3_block_with_txs.block,
+                        )
+                        .await?;
+                        return Ok(true);
                     }
+                    None => return Ok(inserted),
                 }
-            } else {
-                let (txs_len, logs_len) = self.indexer.store_l2_block(l2_block).await?;
-
-                let duration = start.elapsed();
-                log::info!(
-                    "Sync block {}, {} txs, {} logs, duration: {:?}",
-                    current_block_number,
-                    txs_len,
-                    logs_len,
-                    duration,
-                );
-                self.bump_tip().await?;
             }
 
-            return Ok(true);
+            let (txs_len, logs_len) = self.indexer.store_web3_block(web3_block_with_txs).await?;
+
+            let duration = start.elapsed();
+            log::info!(
+                "Sync block {}, {} txs, {} logs, duration: {:?}",
+                current_block_number,
+                txs_len,
+                logs_len,
+                duration,
+            );
+            self.bump_tip().await?;
+
+            prev_block_hash = Some(ckb_types::H256::from_slice(current_block_hash.as_slice())?);
+            prefetch.next_block_number = current_block_number + 1;
+            inserted = true;
         }
 
-        Ok(false)
+        self.prefetch = Some(prefetch);
+        Ok(inserted)
+    }
+
+    async fn prune_orphaned_blocks(&self) -> Result<()> {
+        if let Some(retention) = self.orphan_retention {
+            let pruned = prune_orphaned_blocks(&*POOL, retention).await?;
+            if pruned > 0 {
+                log::info!("Prune {} orphaned blocks", pruned);
+            }
+        }
+        Ok(())
     }
 
     pub async fn run(&mut self) -> Result<()> {
+        self.wait_for_verified_chain().await?;
+        let backfiller = Backfiller {
+            indexer: Arc::clone(&self.indexer),
+            gaps: Gaps::default(),
+            scan_from: Arc::clone(&self.gap_scan_from),
+            scan_interval: self.gap_scan_interval,
+            poll_interval: self.poll_interval,
+            batch_size: self.block_prefetch_window,
+        };
+        // the backfill only stops with the sync
+        smol::future::or(self.sync(), backfiller.run()).await
+    }
+
+    async fn sync(&mut self) -> Result<()> {
+        let mut last_prune: Option<Instant> = None;
+        let mut last_finality_check: Option<Instant> = None;
         loop {
+            if last_prune.map_or(true, |t| t.elapsed() >= self.orphan_prune_interval) {
+                if let Err(err) = self.prune_orphaned_blocks().await {
+                    log::error!("Prune orphaned blocks failed: {}", err);
+                }
+                last_prune = Some(Instant::now());
+            }
+            if last_finality_check.map_or(true, |t| t.elapsed() >= self.finality_check_interval) {
+                if let Err(err) = self.update_finalized_blocks().await {
+                    log::error!("Update finalized blocks failed: {}", err);
+                }
+                last_finality_check = Some(Instant::now());
+            }
+
             match self.insert().await {
                 Ok(result) => {
                     if !result {
-                        let sleep_time = std::time::Duration::from_secs(1);
-                        smol::Timer::after(sleep_time).await;
+                        smol::Timer::after(self.poll_interval).await;
                     }
                 }
                 Err(err) => {
                     let err_ref = err.downcast_ref::<RpcClientError>();
                     if let Some(RpcClientError::ConnectionError(_, _)) = err_ref {
                         log::error!("{}", err);
-                        // wait for 1s
-                        let sleep_time = std::time::Duration::from_secs(1);
-                        smol::Timer::after(sleep_time).await;
+                        smol::Timer::after(self.poll_interval).await;
                         continue;
                     };
                     return Err(err);
@@ -257,6 +661,7 @@ impl Runner {
         start_block_number: Option<u64>,
         end_block_number: Option<u64>,
     ) -> Result<()> {
+        self.wait_for_verified_chain().await?;
         let start_block_number = start_block_number.unwrap_or(0);
         let local_tip = self.tip().await?;
 
@@ -307,15 +712,402 @@ impl Runner {
                     let err_ref = err.downcast_ref::<RpcClientError>();
                     if let Some(RpcClientError::ConnectionError(_, _)) = err_ref {
                         log::error!("{}", err);
-                        // wait for 1s
-                        let sleep_time = std::time::Duration::from_secs(1);
-                        smol::Timer::after(sleep_time).await;
+                        smol::Timer::after(self.poll_interval).await;
+                        continue;
+                    };
+                    return Err(err);
+                }
+            };
+        }
+        Ok(())
+    }
+
+    // Check signatures of blocks in [start_block_number, end_block_number] and
+    // replace their anomalies, end_block_number defaults to the local tip.
+    pub async fn run_audit(
+        &mut self,
+        start_block_number: Option<u64>,
+        end_block_number: Option<u64>,
+    ) -> Result<()> {
+        self.wait_for_verified_chain().await?;
+        let start_block_number = start_block_number.unwrap_or(0);
+        let end_block_number = match end_block_number {
+            Some(n) => n,
+            None => self.tip().await?.unwrap_or(0),
+        };
+
+        log::info!(
+            "Audit from block {} to block {}",
+            start_block_number,
+            end_block_number
+        );
+
+        let loop_start = std::time::Instant::now();
+        let mut total_anomalies = 0;
+        let mut current_block_number = start_block_number;
+        while current_block_number <= end_block_number {
+            match self.indexer.audit_block(current_block_number).await {
+                Ok(Some(anomalies_len)) => {
+                    if anomalies_len > 0 {
+                        log::warn!(
+                            "Audit block {}, {} anomalies",
+                            current_block_number,
+                            anomalies_len
+                        );
+                    }
+                    total_anomalies += anomalies_len;
+                    current_block_number += 1;
+                }
+                Ok(None) => {
+                    return Err(anyhow!("block {} not exist!", current_block_number));
+                }
+                Err(err) => {
+                    let err_ref = err.downcast_ref::<RpcClientError>();
+                    if let Some(RpcClientError::ConnectionError(_, _)) = err_ref {
+                        log::error!("{}", err);
+                        smol::Timer::after(self.poll_interval).await;
                         continue;
                     };
                     return Err(err);
                 }
+            }
+        }
+
+        log::info!(
+            "All blocks have been audited! {} anomalies, total duration: {:?}",
+            total_anomalies,
+            loop_start.elapsed()
+        );
+        Ok(())
+    }
+
+    // Re-derive blocks in [start_block_number, end_block_number] from godwoken and compare them
+    // with the database, end_block_number defaults to the local tip.
+    //
+    // Each mismatch is printed to stdout as a JSON line. With `fix`, divergent blocks are
+    // replaced by the derived ones, otherwise indexed rows are left untouched.
+    pub async fn run_verify(
+        &mut self,
+        start_block_number: Option<u64>,
+        end_block_number: Option<u64>,
+        fix: bool,
+    ) -> Result<()> {
+        self.wait_for_verified_chain().await?;
+        let start_block_number = start_block_number.unwrap_or(0);
+        let end_block_number = match end_block_number {
+            Some(n) => n,
+            None => self.tip().await?.unwrap_or(0),
+        };
+
+        log::info!(
+            "Verify from block {} to block {}",
+            start_block_number,
+            end_block_number
+        );
+
+        let loop_start = std::time::Instant::now();
+        let (_prefetch_task, prefetched_blocks) =
+            self.prefetch_blocks(start_block_number, Some(end_block_number));
+        let mut next_block_number = start_block_number;
+        let mut total_mismatches = 0;
+        let mut divergent_blocks = 0;
+        while let Ok(prefetched) = prefetched_blocks.recv().await {
+            let web3_block_with_txs = prefetched?;
+            let block_number = web3_block_with_txs.block.number;
+
+            let mismatches = verify_block(&web3_block_with_txs).await?;
+            if !mismatches.is_empty() {
+                for mismatch in &mismatches {
+                    println!("{}", serde_json::to_string(mismatch)?);
+                }
+                total_mismatches += mismatches.len();
+                divergent_blocks += 1;
+                if fix {
+                    self.indexer.replace_web3_block(web3_block_with_txs).await?;
+                    log::info!(
+                        "Fix block {}, {} mismatches",
+                        block_number,
+                        mismatches.len()
+                    );
+                }
+            }
+            next_block_number = block_number + 1;
+        }
+        if next_block_number <= end_block_number {
+            return Err(anyhow!("block {} not exist!", next_block_number));
+        }
+
+        log::info!(
+            "All blocks have been verified! {} divergent blocks, {} mismatches, total duration: {:?}",
+            divergent_blocks,
+            total_mismatches,
+            loop_start.elapsed()
+        );
+        Ok(())
+    }
+
+    // Delete all local blocks above `target_block_number`, the sync mode continues from it.
+    //
+    // The hash of the target block must match godwoken. Blocks are deleted from the tip
+    // downwards in batches of `batch.rollback_blocks`, each batch in its own database transaction,
+    // so an interrupted rollback leaves a consistent chain and can simply be run again.
+    pub async fn run_rollback(&mut self, target_block_number: u64) -> Result<()> {
+        self.wait_for_verified_chain().await?;
+        let local_tip = match self.tip().await? {
+            Some(t) if t > target_block_number => t,
+            _ => {
+                log::info!("No blocks above block {}", target_block_number);
+                return Ok(());
+            }
+        };
+
+        let db_block_hash = self
+            .get_db_block_hash(target_block_number)
+            .await?
+            .ok_or_else(|| anyhow!("block {} not exist in database!", target_block_number))?;
+        let chain_block_hash = self
+            .godwoken_async_client
+            .get_block_hash(target_block_number)
+            .await?
+            .ok_or_else(|| anyhow!("block {} not exist on godwoken!", target_block_number))?;
+        if db_block_hash != chain_block_hash {
+            return Err(anyhow!(
+                "block {} hash 0x{} doesn't match godwoken 0x{}, rollback to a lower block",
+                target_block_number,
+                db_block_hash,
+                chain_block_hash
+            ));
+        }
+
+        log::info!(
+            "Rollback from block {} to block {}",
+            local_tip,
+            target_block_number
+        );
+        let loop_start = std::time::Instant::now();
+        let mut total_deleted: Vec<(&'static str, u64)> = vec![];
+        let mut batch_end = local_tip;
+        while batch_end > target_block_number {
+            let batch_start = std::cmp::max(
+                target_block_number + 1,
+                batch_end.saturating_sub(self.rollback_batch_size.saturating_sub(1)),
+            );
+
+            let pool = &*POOL;
+            let mut pg_tx = pool.begin().await?;
+            if self.archive_orphaned_blocks {
+                let orphaned_at = SystemTime::now().into();
+                for number in batch_start..=batch_end {
+                    archive_orphaned_block(number, None, orphaned_at, &mut pg_tx).await?;
+                }
+            }
+            let mut deleted = self.delete_blocks_from(batch_start, &mut pg_tx).await?;
+            let accounts_len = self
+                .indexer
+                .rollback_accounts(batch_start, &mut pg_tx)
+                .await?;
+            deleted.push(("accounts", accounts_len));
+            pg_tx.commit().await?;
+            self.local_tip = Some(batch_start - 1);
+
+            log::info!("Rollback blocks {} to {}", batch_start, batch_end);
+            for (table, rows) in deleted {
+                match total_deleted.iter_mut().find(|(t, _)| *t == table) {
+                    Some((_, total)) => *total += rows,
+                    None => total_deleted.push((table, rows)),
+                }
+            }
+            batch_end = batch_start - 1;
+        }
+
+        for (table, rows) in total_deleted {
+            log::info!("Rollback deleted {} rows from {}", rows, table);
+        }
+        log::info!(
+            "All blocks above block {} have been rolled back! Total duration: {:?}",
+            target_block_number,
+            loop_start.elapsed()
+        );
+        Ok(())
+    }
+}
+
+// Print the local tip, the finalized head and the gaps from the database, and the godwoken tip
+// unless godwoken can't be reached. The chain spec isn't needed, so it runs without godwoken.
+pub async fn run_status(godwoken_rpc_url: &str) -> Result<()> {
+    let local_tip = get_db_tip_number().await?;
+    let finalized = get_db_finalized_number().await?;
+    let (gaps, _) = scan_gaps(&*POOL, 0).await?;
+    let format_number = |n: Option<u64>| n.map_or("null".to_string(), |n| n.to_string());
+
+    println!("local_tip: {}", format_number(local_tip));
+    println!("finalized: {}", format_number(finalized));
+    let client = GodwokenAsyncClient::with_url(godwoken_rpc_url)?;
+    match get_chain_tip_number(&client).await {
+        Ok(chain_tip) => {
+            println!("godwoken_tip: {}", chain_tip);
+            // number of godwoken blocks not indexed yet
+            let lag = match local_tip {
+                Some(t) => chain_tip.saturating_sub(t),
+                None => chain_tip + 1,
             };
+            println!("lag: {}", lag);
         }
+        Err(err) => {
+            log::warn!("Get godwoken tip failed: {}", err);
+            println!("godwoken_tip: unavailable");
+            println!("lag: unavailable");
+        }
+    }
+    println!("missing_blocks: {}", gaps.missing_len());
+    println!("discontinuities: {}", gaps.discontinuities.len());
+    Ok(())
+}
+
+async fn get_db_tip_number() -> Result<Option<u64>> {
+    let row: Option<(Decimal,)> =
+        sqlx::query_as("select number from blocks order by number desc limit 1;")
+            .fetch_optional(&*POOL)
+            .await?;
+
+    let num = row.and_then(|(n,)| n.to_u64());
+    Ok(num)
+}
+
+async fn get_chain_tip_number(client: &GodwokenAsyncClient) -> Result<u64> {
+    let tip_block_hash = client
+        .get_tip_block_hash()
+        .await?
+        .ok_or_else(|| anyhow!("tip block hash not found"))?;
+    let tip_block = client
+        .get_block(&tip_block_hash)
+        .await?
+        .ok_or_else(|| anyhow!("tip block 0x{} not exist!", tip_block_hash))?;
+    Ok(tip_block.block.raw.number.value())
+}
+
+async fn get_db_finalized_number() -> Result<Option<u64>> {
+    let row: Option<(Decimal,)> =
+        sqlx::query_as("select number from blocks where status = $1 order by number desc limit 1;")
+            .bind(BlockStatus::Finalized.as_str())
+            .fetch_optional(&*POOL)
+            .await?;
+
+    let num = row.and_then(|(n,)| n.to_u64());
+    Ok(num)
+}
+
+// Scans the database for gaps and backfills them, concurrently with the sync of new blocks.
+//
+// Each scan continues from the last block of the previous one, a reorg moves `scan_from` back
+// to the common ancestor. Blocks are written while holding `scan_from`, so that they are never
+// written concurrently with a rollback.
+struct Backfiller {
+    indexer: Arc<Web3Indexer>,
+    // Gaps found by the last scan and not backfilled yet
+    gaps: Gaps,
+    scan_from: Arc<Mutex<u64>>,
+    scan_interval: Duration,
+    poll_interval: Duration,
+    // Max number of blocks backfilled in one batch
+    batch_size: usize,
+}
+
+impl Backfiller {
+    async fn run(mut self) -> Result<()> {
+        let mut last_scan: Option<Instant> = None;
+        loop {
+            if last_scan.map_or(true, |t| t.elapsed() >= self.scan_interval) {
+                if let Err(err) = self.scan().await {
+                    log::error!("Scan gaps failed: {}", err);
+                }
+                last_scan = Some(Instant::now());
+            }
+            if self.gaps.is_empty() {
+                smol::Timer::after(self.poll_interval).await;
+                continue;
+            }
+            if let Err(err) = self.backfill().await {
+                log::error!("Backfill gaps failed: {}", err);
+                smol::Timer::after(self.poll_interval).await;
+            }
+        }
+    }
+
+    // Find missing blocks and parent hash discontinuities in the blocks indexed since the last
+    // scan, unless the gaps of the last scan are still being backfilled.
+    async fn scan(&mut self) -> Result<()> {
+        if !self.gaps.is_empty() {
+            return Ok(());
+        }
+        let mut scan_from = self.scan_from.lock().await;
+        let (gaps, last) = scan_gaps(&*POOL, *scan_from).await?;
+        if let Some(last) = last {
+            *scan_from = last;
+        }
+        drop(scan_from);
+        if !gaps.is_empty() {
+            log::warn!(
+                "Found {} missing blocks and {} parent hash discontinuities, backfilling",
+                gaps.missing_len(),
+                gaps.discontinuities.len()
+            );
+        }
+        self.gaps = gaps;
+        Ok(())
+    }
+
+    // Backfill at most `batch_size` blocks of the gaps. If a block fails, the remaining gaps are
+    // dropped and found again by the next scan, which starts below the failed block.
+    async fn backfill(&mut self) -> Result<()> {
+        let tip = self.indexer.tip_number().await?;
+        for backfill in self.gaps.take(self.batch_size, tip) {
+            let number = match backfill {
+                Backfill::Missing(number) | Backfill::Discontinuity(number) => number,
+            };
+            if let Err(err) = self.backfill_block(backfill).await {
+                self.gaps = Gaps::default();
+                let mut scan_from = self.scan_from.lock().await;
+                *scan_from = (*scan_from).min(number.saturating_sub(1));
+                return Err(anyhow!("block {}: {}", number, err));
+            }
+        }
+        Ok(())
+    }
+
+    // Missing blocks are inserted, and the blocks around a discontinuity which don't match
+    // godwoken are replaced.
+    async fn backfill_block(&self, backfill: Backfill) -> Result<()> {
+        let number = match backfill {
+            Backfill::Missing(number) | Backfill::Discontinuity(number) => number,
+        };
+        let web3_block_with_txs = self
+            .indexer
+            .prefetch_l2_block(number)
+            .await?
+            .ok_or_else(|| anyhow!("block {} not exist!", number))?;
+
+        let _scan_from = self.scan_from.lock().await;
+        // rolled back while fetching, indexed again by the sync
+        let tip = self.indexer.tip_number().await?;
+        if tip.map_or(true, |tip| number > tip) {
+            return Ok(());
+        }
+        let (txs_len, logs_len) = match backfill {
+            Backfill::Missing(_) => self.indexer.store_web3_block(web3_block_with_txs).await?,
+            Backfill::Discontinuity(_) => {
+                if verify_block(&web3_block_with_txs).await?.is_empty() {
+                    return Ok(());
+                }
+                self.indexer.replace_web3_block(web3_block_with_txs).await?
+            }
+        };
+        log::info!(
+            "Backfill block {}, {} txs, {} logs",
+            number,
+            txs_len,
+            logs_len
+        );
         Ok(())
     }
 }
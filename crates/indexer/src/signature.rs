@@ -0,0 +1,227 @@
+use anyhow::{anyhow, Result};
+use secp256k1::{
+    ecdsa::{RecoverableSignature, RecoveryId},
+    Message, Secp256k1, VerifyOnly,
+};
+use sha3::{Digest, Keccak256};
+
+use crate::types::{Transaction, TxType};
+
+lazy_static::lazy_static! {
+    static ref SECP256K1: Secp256k1<VerifyOnly> = Secp256k1::verification_only();
+}
+
+// Recover the eth address which signed the reconstructed eth transaction.
+pub fn recover_signer(tx: &Transaction) -> Result<[u8; 20]> {
+    let message_hash = Keccak256::digest(&tx.signing_payload());
+    let message = Message::from_slice(&message_hash)?;
+
+    // v is the recovery id, some signers add 27 to it
+    let recovery_id = match tx.v {
+        0 | 1 => tx.v,
+        27 | 28 => tx.v - 27,
+        v => return Err(anyhow!("invalid signature v: {}", v)),
+    };
+    let mut compact = [0u8; 64];
+    compact[0..32].copy_from_slice(&tx.r);
+    compact[32..64].copy_from_slice(&tx.s);
+    let signature =
+        RecoverableSignature::from_compact(&compact, RecoveryId::from_i32(recovery_id as i32)?)?;
+
+    let public_key = SECP256K1.recover_ecdsa(&message, &signature)?;
+    // eth address is the last 20 bytes of keccak256(uncompressed public key without the 0x04 prefix)
+    let public_key_hash = Keccak256::digest(&public_key.serialize_uncompressed()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&public_key_hash[12..32]);
+    Ok(address)
+}
+
+// Resolve the EIP-2718 type of a polyjuice transaction from its signature. Polyjuice args
+// carry no envelope type, so each type is tried in turn and the first one whose signing
+// payload recovers to from_address is kept. The transaction stays legacy if none does, the
+// mismatch is then recorded as an anomaly. It costs up to three signature recoveries, so
+// it only runs when signatures are verified.
+pub fn resolve_tx_type(tx: &mut Transaction) {
+    // only polyjuice transactions are signed over the eth transaction
+    if tx.chain_id.is_none() {
+        return;
+    }
+    for tx_type in [TxType::Legacy, TxType::AccessList, TxType::DynamicFee] {
+        tx.tx_type = tx_type;
+        if matches!(recover_signer(tx), Ok(address) if address == tx.from_address) {
+            return;
+        }
+    }
+    tx.tx_type = TxType::Legacy;
+}
+
+#[cfg(test)]
+mod tests {
+    use gw_common::H256;
+    use gw_types::U256;
+
+    use super::*;
+    use crate::helper::from_hex;
+
+    // address of the private key 0x4646...46 used by the EIP-155 example
+    const SIGNER: &str = "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f";
+
+    fn bytes32(value: &str) -> [u8; 32] {
+        let mut buf = [0u8; 32];
+        buf.copy_from_slice(&from_hex(value));
+        buf
+    }
+
+    fn address(value: &str) -> [u8; 20] {
+        let mut buf = [0u8; 20];
+        buf.copy_from_slice(&from_hex(value));
+        buf
+    }
+
+    // Transfer of 1 ether to 0x3535...35 with nonce 9 and gas price 20 gwei, as a polyjuice
+    // transaction signed with (r, s, v)
+    fn signed_tx(chain_id: u64, data: &str, r: &str, s: &str, v: u8) -> Transaction {
+        Transaction::new(
+            H256::zero(),
+            Some(chain_id),
+            0,
+            H256::zero(),
+            0,
+            address(SIGNER),
+            Some([0x35u8; 20]),
+            U256::from(1_000_000_000_000_000_000u128),
+            9,
+            21_000,
+            20_000_000_000,
+            from_hex(data),
+            bytes32(r),
+            bytes32(s),
+            v,
+            0,
+            0,
+            None,
+            0,
+            Some(0),
+            None,
+        )
+    }
+
+    // The example of EIP-155, see https://eips.ethereum.org/EIPS/eip-155
+    fn legacy_tx() -> Transaction {
+        signed_tx(
+            1,
+            "0x",
+            "0x28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276",
+            "0x67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+            0,
+        )
+    }
+
+    #[test]
+    fn test_recover_signer() {
+        assert_eq!(recover_signer(&legacy_tx()).unwrap(), address(SIGNER));
+
+        // some signers add 27 to the recovery id
+        let mut tx = legacy_tx();
+        tx.v = 27;
+        assert_eq!(recover_signer(&tx).unwrap(), address(SIGNER));
+    }
+
+    #[test]
+    fn test_recover_tampered_tx() {
+        let mut tx = legacy_tx();
+        tx.value = U256::from(2_000_000_000_000_000_000u128);
+        assert_ne!(recover_signer(&tx).unwrap(), address(SIGNER));
+
+        let mut tx = legacy_tx();
+        tx.nonce = 10;
+        assert_ne!(recover_signer(&tx).unwrap(), address(SIGNER));
+
+        // replayed on another chain
+        let mut tx = legacy_tx();
+        tx.chain_id = Some(71402);
+        assert_ne!(recover_signer(&tx).unwrap(), address(SIGNER));
+
+        let mut tx = legacy_tx();
+        tx.v = 1;
+        assert_ne!(recover_signer(&tx).unwrap(), address(SIGNER));
+
+        let mut tx = legacy_tx();
+        tx.v = 2;
+        assert!(recover_signer(&tx).is_err());
+
+        // zero signature of a non 65 bytes long L2 signature
+        let mut tx = legacy_tx();
+        tx.r = [0u8; 32];
+        tx.s = [0u8; 32];
+        assert!(recover_signer(&tx).is_err());
+    }
+
+    #[test]
+    fn test_resolve_legacy_tx() {
+        let mut tx = legacy_tx();
+        resolve_tx_type(&mut tx);
+        assert_eq!(tx.tx_type, TxType::Legacy);
+        assert_eq!(tx.to_rlp(), from_hex("0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"));
+        assert_eq!(
+            tx.compute_eth_tx_hash().as_slice(),
+            from_hex("0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788")
+        );
+    }
+
+    #[test]
+    fn test_resolve_access_list_tx() {
+        let mut tx = signed_tx(
+            71402,
+            "0x",
+            "0x7d5821b8cf4386e0d57defde13df41fa3d629c6cbc47d7463e4a7c8aff100c68",
+            "0x4ccbe5e075ca4ef4a1377858dcac90e7a68553b7a7298253c910d1b2de99b811",
+            1,
+        );
+        resolve_tx_type(&mut tx);
+        assert_eq!(tx.tx_type, TxType::AccessList);
+        // not carried by godwoken, stored as NULL
+        assert!(tx.access_list.is_none());
+        assert_eq!(tx.max_fee_per_gas, None);
+        assert_eq!(tx.to_rlp(), from_hex("0x01f871830116ea098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a764000080c001a07d5821b8cf4386e0d57defde13df41fa3d629c6cbc47d7463e4a7c8aff100c68a04ccbe5e075ca4ef4a1377858dcac90e7a68553b7a7298253c910d1b2de99b811"));
+        assert_eq!(
+            tx.compute_eth_tx_hash().as_slice(),
+            from_hex("0xa2c8313e779287d14092af5e5b3df499965bf14628207799e1581d970e19bf58")
+        );
+    }
+
+    #[test]
+    fn test_resolve_dynamic_fee_tx() {
+        let mut tx = signed_tx(
+            71402,
+            "0x12345678",
+            "0x8cb31f3d904661ce8de162d8cc1922fbd8f768f69f27fc0c52d0647e41cdec41",
+            "0x511967a4e49bd01bc452c65369b304d28b4b01d14397d45f7d5a19434e604250",
+            1,
+        );
+        resolve_tx_type(&mut tx);
+        assert_eq!(tx.tx_type, TxType::DynamicFee);
+        assert_eq!(tx.max_priority_fee_per_gas, None);
+        assert_eq!(tx.max_fee_per_gas, None);
+        assert_eq!(tx.to_rlp(), from_hex("0x02f87b830116ea098504a817c8008504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008412345678c001a08cb31f3d904661ce8de162d8cc1922fbd8f768f69f27fc0c52d0647e41cdec41a0511967a4e49bd01bc452c65369b304d28b4b01d14397d45f7d5a19434e604250"));
+        assert_eq!(
+            tx.compute_eth_tx_hash().as_slice(),
+            from_hex("0xfe1d44ac363f10a4d3411cb179b5744c1e3cf5191055f1e7c6230dcf47bd9330")
+        );
+    }
+
+    #[test]
+    fn test_unresolved_tx_stays_legacy() {
+        let mut tx = legacy_tx();
+        tx.from_address = [0x11u8; 20];
+        resolve_tx_type(&mut tx);
+        assert_eq!(tx.tx_type, TxType::Legacy);
+        assert_eq!(tx.max_fee_per_gas, None);
+
+        // non-polyjuice transactions are not signed over the eth transaction
+        let mut tx = legacy_tx();
+        tx.chain_id = None;
+        resolve_tx_type(&mut tx);
+        assert_eq!(tx.tx_type, TxType::Legacy);
+    }
+}
@@ -1,9 +1,14 @@
-use gw_common::H256;
+use gw_common::{registry_address::RegistryAddress, H256};
+use gw_jsonrpc_types::godwoken::L2BlockStatus;
 use gw_types::U256;
 use sha3::{Digest, Keccak256};
 use sqlx::types::chrono::{DateTime, Utc};
 
+use crate::accounts::Account;
+
 type Address = [u8; 20];
+// 2048-bit logs bloom
+pub type Bloom = [u8; 256];
 
 #[derive(Debug)]
 pub struct Block {
@@ -15,6 +20,49 @@ pub struct Block {
     pub miner: Address,
     pub size: usize,
     pub timestamp: DateTime<Utc>,
+    pub logs_bloom: Bloom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockStatus {
+    Unfinalized,
+    Finalized,
+    Reverted,
+}
+
+impl BlockStatus {
+    // Value of `blocks.status`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockStatus::Unfinalized => "unfinalized",
+            BlockStatus::Finalized => "finalized",
+            BlockStatus::Reverted => "reverted",
+        }
+    }
+}
+
+impl From<L2BlockStatus> for BlockStatus {
+    fn from(status: L2BlockStatus) -> Self {
+        match status {
+            L2BlockStatus::Unfinalized => BlockStatus::Unfinalized,
+            L2BlockStatus::Finalized => BlockStatus::Finalized,
+            L2BlockStatus::Reverted => BlockStatus::Reverted,
+        }
+    }
+}
+
+// EIP-2718 transaction type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxType {
+    Legacy = 0,
+    AccessList = 1,
+    DynamicFee = 2,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessListItem {
+    pub address: Address,
+    pub storage_keys: Vec<H256>,
 }
 
 #[derive(Debug)]
@@ -38,6 +86,17 @@ pub struct Transaction {
     pub gas_used: u128,
     pub contract_address: Option<Address>,
     pub exit_code: u8,
+    // evmc status code in the polyjuice system log, None for non-polyjuice transactions
+    pub status_code: Option<i32>,
+    // return data of a failed transaction, if godwoken returns it with the receipt
+    pub revert_data: Option<Vec<u8>>,
+    pub logs_bloom: Bloom,
+    pub tx_type: TxType,
+    // only for EIP-2930 and EIP-1559 transactions, None if unknown
+    pub access_list: Option<Vec<AccessListItem>>,
+    // only for EIP-1559 transactions, None if unknown
+    pub max_priority_fee_per_gas: Option<u128>,
+    pub max_fee_per_gas: Option<u128>,
 }
 
 impl Transaction {
@@ -62,6 +121,8 @@ impl Transaction {
         gas_used: u128,
         contract_address: Option<Address>,
         exit_code: u8,
+        status_code: Option<i32>,
+        revert_data: Option<Vec<u8>>,
     ) -> Transaction {
         Transaction {
             gw_tx_hash,
@@ -83,6 +144,23 @@ impl Transaction {
             gas_used,
             contract_address,
             exit_code,
+            status_code,
+            revert_data,
+            logs_bloom: [0u8; 256], // should update later
+            // polyjuice args carry no envelope type, see `signature::resolve_tx_type`
+            tx_type: TxType::Legacy,
+            access_list: None,
+            max_priority_fee_per_gas: None,
+            max_fee_per_gas: None,
+        }
+    }
+
+    // Value of `transactions.failure_reason`, with the decoded revert reason if there is one
+    pub fn failure_reason(&self) -> Option<String> {
+        let reason = PolyjuiceStatus::from(self.status_code?).failure_reason()?;
+        match self.revert_data.as_deref().and_then(decode_revert_reason) {
+            Some(revert_reason) => Some(format!("{}: {}", reason, revert_reason)),
+            None => Some(reason),
         }
     }
 
@@ -101,20 +179,45 @@ impl Transaction {
     }
 
     pub fn to_rlp(&self) -> Vec<u8> {
+        match self.tx_type {
+            TxType::Legacy => self.to_legacy_rlp(),
+            TxType::AccessList | TxType::DynamicFee => self.to_typed_rlp(true),
+        }
+    }
+
+    // The payload hashed and signed by the sender, i.e. the encoded transaction without signature
+    pub fn signing_payload(&self) -> Vec<u8> {
+        match self.tx_type {
+            TxType::Legacy => {
+                let mut s = rlp::RlpStream::new();
+                s.begin_unbounded_list()
+                    .append(&self.nonce)
+                    .append(&self.gas_price)
+                    .append(&self.gas_limit);
+                self.append_to_address(&mut s);
+                s.append(&self.value).append(&self.data);
+                // EIP-155: chain_id, 0, 0
+                match self.chain_id {
+                    Some(id) if id != 0 => {
+                        s.append(&id).append(&0u8).append(&0u8);
+                    }
+                    _ => {}
+                }
+                s.finalize_unbounded_list();
+                s.out().freeze().to_vec()
+            }
+            TxType::AccessList | TxType::DynamicFee => self.to_typed_rlp(false),
+        }
+    }
+
+    fn to_legacy_rlp(&self) -> Vec<u8> {
         // RLP encode
         let mut s = rlp::RlpStream::new();
         s.begin_unbounded_list()
             .append(&self.nonce)
             .append(&self.gas_price)
             .append(&self.gas_limit);
-        match self.to_address.as_ref() {
-            Some(addr) => {
-                s.append(&addr.to_vec());
-            }
-            None => {
-                s.append(&vec![0u8; 0]);
-            }
-        };
+        self.append_to_address(&mut s);
         // r & s should be integer format in RLP
         let r_num = U256::from(&self.r);
         let s_num = U256::from(&self.s);
@@ -127,6 +230,58 @@ impl Transaction {
         s.out().freeze().to_vec()
     }
 
+    // EIP-2718 envelope: tx_type || rlp(payload)
+    // EIP-2930: [chain_id, nonce, gas_price, gas_limit, to, value, data, access_list, y_parity, r, s]
+    // EIP-1559: [chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, to, value, data, access_list, y_parity, r, s]
+    fn to_typed_rlp(&self, with_signature: bool) -> Vec<u8> {
+        let mut s = rlp::RlpStream::new();
+        s.begin_unbounded_list()
+            .append(&self.chain_id.unwrap_or_default())
+            .append(&self.nonce);
+        // L2 transactions carry no access list and pay a single gas price, unknown fields are
+        // encoded as signed by the sender: an empty access list and the gas price as both fees
+        if self.tx_type == TxType::DynamicFee {
+            s.append(&self.max_priority_fee_per_gas.unwrap_or(self.gas_price))
+                .append(&self.max_fee_per_gas.unwrap_or(self.gas_price));
+        } else {
+            s.append(&self.gas_price);
+        }
+        s.append(&self.gas_limit);
+        self.append_to_address(&mut s);
+        s.append(&self.value).append(&self.data);
+        let access_list = self.access_list.as_deref().unwrap_or_default();
+        s.begin_list(access_list.len());
+        for item in access_list {
+            s.begin_list(2).append(&item.address.to_vec());
+            s.begin_list(item.storage_keys.len());
+            for key in &item.storage_keys {
+                s.append(&key.as_slice().to_vec());
+            }
+        }
+        if with_signature {
+            // v is the y parity without replay protection
+            let r_num = U256::from(&self.r);
+            let s_num = U256::from(&self.s);
+            s.append(&self.v).append(&r_num).append(&s_num);
+        }
+        s.finalize_unbounded_list();
+
+        let mut buf = vec![self.tx_type as u8];
+        buf.extend_from_slice(&s.out());
+        buf
+    }
+
+    fn append_to_address(&self, s: &mut rlp::RlpStream) {
+        match self.to_address.as_ref() {
+            Some(addr) => {
+                s.append(&addr.to_vec());
+            }
+            None => {
+                s.append(&vec![0u8; 0]);
+            }
+        };
+    }
+
     pub fn compute_eth_tx_hash(&self) -> gw_common::H256 {
         // RLP encode
         let rlp_data = self.to_rlp();
@@ -139,6 +294,151 @@ impl Transaction {
     }
 }
 
+// evmc status codes returned by polyjuice,
+// see https://github.com/ethereum/evmc/blob/v9.0.0/include/evmc/evmc.h#L212
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolyjuiceStatus {
+    Success,
+    Failure,
+    Revert,
+    OutOfGas,
+    InvalidInstruction,
+    UndefinedInstruction,
+    StackOverflow,
+    StackUnderflow,
+    BadJumpDestination,
+    InvalidMemoryAccess,
+    CallDepthExceeded,
+    StaticModeViolation,
+    PrecompileFailure,
+    ContractValidationFailure,
+    ArgumentOutOfRange,
+    WasmUnreachableInstruction,
+    WasmTrap,
+    InsufficientBalance,
+    InternalError,
+    Rejected,
+    OutOfMemory,
+    Unknown(i32),
+}
+
+impl PolyjuiceStatus {
+    // Value of `transactions.failure_reason`, None for success
+    pub fn failure_reason(&self) -> Option<String> {
+        let reason = match self {
+            PolyjuiceStatus::Success => return None,
+            PolyjuiceStatus::Failure => "failure",
+            PolyjuiceStatus::Revert => "revert",
+            PolyjuiceStatus::OutOfGas => "out of gas",
+            PolyjuiceStatus::InvalidInstruction => "invalid instruction",
+            PolyjuiceStatus::UndefinedInstruction => "undefined instruction",
+            PolyjuiceStatus::StackOverflow => "stack overflow",
+            PolyjuiceStatus::StackUnderflow => "stack underflow",
+            PolyjuiceStatus::BadJumpDestination => "bad jump destination",
+            PolyjuiceStatus::InvalidMemoryAccess => "invalid memory access",
+            PolyjuiceStatus::CallDepthExceeded => "call depth exceeded",
+            PolyjuiceStatus::StaticModeViolation => "static mode violation",
+            PolyjuiceStatus::PrecompileFailure => "precompile failure",
+            PolyjuiceStatus::ContractValidationFailure => "contract validation failure",
+            PolyjuiceStatus::ArgumentOutOfRange => "argument out of range",
+            PolyjuiceStatus::WasmUnreachableInstruction => "wasm unreachable instruction",
+            PolyjuiceStatus::WasmTrap => "wasm trap",
+            PolyjuiceStatus::InsufficientBalance => "insufficient balance",
+            PolyjuiceStatus::InternalError => "internal error",
+            PolyjuiceStatus::Rejected => "rejected",
+            PolyjuiceStatus::OutOfMemory => "out of memory",
+            PolyjuiceStatus::Unknown(code) => return Some(format!("unknown status code {}", code)),
+        };
+        Some(reason.to_string())
+    }
+}
+
+impl From<i32> for PolyjuiceStatus {
+    fn from(code: i32) -> Self {
+        match code {
+            0 => PolyjuiceStatus::Success,
+            1 => PolyjuiceStatus::Failure,
+            2 => PolyjuiceStatus::Revert,
+            3 => PolyjuiceStatus::OutOfGas,
+            4 => PolyjuiceStatus::InvalidInstruction,
+            5 => PolyjuiceStatus::UndefinedInstruction,
+            6 => PolyjuiceStatus::StackOverflow,
+            7 => PolyjuiceStatus::StackUnderflow,
+            8 => PolyjuiceStatus::BadJumpDestination,
+            9 => PolyjuiceStatus::InvalidMemoryAccess,
+            10 => PolyjuiceStatus::CallDepthExceeded,
+            11 => PolyjuiceStatus::StaticModeViolation,
+            12 => PolyjuiceStatus::PrecompileFailure,
+            13 => PolyjuiceStatus::ContractValidationFailure,
+            14 => PolyjuiceStatus::ArgumentOutOfRange,
+            15 => PolyjuiceStatus::WasmUnreachableInstruction,
+            16 => PolyjuiceStatus::WasmTrap,
+            17 => PolyjuiceStatus::InsufficientBalance,
+            -1 => PolyjuiceStatus::InternalError,
+            -2 => PolyjuiceStatus::Rejected,
+            -3 => PolyjuiceStatus::OutOfMemory,
+            code => PolyjuiceStatus::Unknown(code),
+        }
+    }
+}
+
+// Selector of solidity `Error(string)`, raised by `require` and `revert`
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+// Selector of solidity `Panic(uint256)`, raised by failed assertions and checked arithmetic
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+// Decode the reason of a revert from its return data, None if the data is neither an
+// `Error(string)` nor a `Panic(uint256)` payload,
+// see https://docs.soliditylang.org/en/v0.8.13/control-structures.html#panic-via-assert-and-error-via-require
+pub fn decode_revert_reason(return_data: &[u8]) -> Option<String> {
+    if return_data.len() < 4 {
+        return None;
+    }
+    let (selector, data) = return_data.split_at(4);
+    if selector == ERROR_SELECTOR {
+        match ethabi::decode(&[ethabi::ParamType::String], data)
+            .ok()?
+            .as_slice()
+        {
+            [ethabi::Token::String(reason)] => Some(reason.clone()),
+            _ => None,
+        }
+    } else if selector == PANIC_SELECTOR {
+        match ethabi::decode(&[ethabi::ParamType::Uint(256)], data)
+            .ok()?
+            .as_slice()
+        {
+            [ethabi::Token::Uint(code)] => match panic_reason(code) {
+                Some(reason) => Some(format!("panic code {:#x} ({})", code, reason)),
+                None => Some(format!("panic code {:#x}", code)),
+            },
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+// Same as the panic reasons of api-server
+fn panic_reason(code: &ethabi::Uint) -> Option<&'static str> {
+    if code.bits() > 8 {
+        return None;
+    }
+    let reason = match code.low_u32() {
+        0x01 => "Assertion error",
+        0x11 => "Arithmetic operation underflowed or overflowed outside of an unchecked block",
+        0x12 => "Division or modulo division by zero",
+        0x21 => "Tried to convert a value into an enum, but the value was too big or negative",
+        0x22 => "Incorrectly encoded storage byte array",
+        0x31 => ".pop() was called on an empty array",
+        0x32 => "Array accessed at an out-of-bounds or negative index",
+        0x41 => "Too much memory was allocated, or an array was created that is too large",
+        0x51 => "Called a zero-initialized variable of internal function type",
+        _ => return None,
+    };
+    Some(reason)
+}
+
 #[derive(Debug)]
 pub struct Log {
     pub transaction_hash: H256,
@@ -176,8 +476,495 @@ impl Log {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenTransferKind {
+    Transfer,
+    Fee,
+}
+
+impl TokenTransferKind {
+    // Value of `token_transfers.kind`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenTransferKind::Transfer => "transfer",
+            TokenTransferKind::Fee => "fee",
+        }
+    }
+}
+
+// SUDT transfer or fee payment emitted by godwoken, including the ones inside contract calls
+#[derive(Debug)]
+pub struct TokenTransfer {
+    pub transaction_hash: H256,
+    pub transaction_index: u32,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub sudt_id: u32,
+    pub from_address: RegistryAddress,
+    pub to_address: RegistryAddress,
+    pub amount: U256,
+    pub kind: TokenTransferKind,
+    // index in block, like `log_index`
+    pub transfer_index: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStandard {
+    Erc20,
+    Erc721,
+    Erc1155,
+}
+
+impl TokenStandard {
+    // Value of `erc_transfers.standard` and `erc_approvals.standard`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenStandard::Erc20 => "erc20",
+            TokenStandard::Erc721 => "erc721",
+            TokenStandard::Erc1155 => "erc1155",
+        }
+    }
+}
+
+// Decoded from a `Transfer`, `TransferSingle` or `TransferBatch` log
+#[derive(Debug)]
+pub struct ErcTransfer {
+    pub transaction_hash: H256,
+    pub transaction_index: u32,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub log_index: u32,
+    // index in `TransferBatch`, 0 for other events
+    pub batch_index: u32,
+    pub standard: TokenStandard,
+    pub token_contract: Address,
+    // only ERC-1155 events have an operator
+    pub operator: Option<Address>,
+    pub from_address: Address,
+    pub to_address: Address,
+    // None for ERC-20
+    pub token_id: Option<ethabi::Uint>,
+    // 1 for ERC-721
+    pub amount: ethabi::Uint,
+}
+
+impl ErcTransfer {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        log: &Log,
+        batch_index: u32,
+        standard: TokenStandard,
+        operator: Option<Address>,
+        from_address: Address,
+        to_address: Address,
+        token_id: Option<ethabi::Uint>,
+        amount: ethabi::Uint,
+    ) -> ErcTransfer {
+        ErcTransfer {
+            transaction_hash: log.transaction_hash,
+            transaction_index: log.transaction_index,
+            block_number: log.block_number,
+            block_hash: log.block_hash,
+            log_index: log.log_index,
+            batch_index,
+            standard,
+            token_contract: log.address,
+            operator,
+            from_address,
+            to_address,
+            token_id,
+            amount,
+        }
+    }
+}
+
+// Decoded from an `Approval` log
+#[derive(Debug)]
+pub struct ErcApproval {
+    pub transaction_hash: H256,
+    pub transaction_index: u32,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub log_index: u32,
+    pub standard: TokenStandard,
+    pub token_contract: Address,
+    pub owner: Address,
+    pub spender: Address,
+    // None for ERC-20
+    pub token_id: Option<ethabi::Uint>,
+    // None for ERC-721
+    pub amount: Option<ethabi::Uint>,
+}
+
+// Standard ethereum bloom, each of the address and topics of a log sets 3 bits
+// picked from its keccak256 hash.
+pub fn logs_bloom<'a>(logs: impl IntoIterator<Item = &'a Log>) -> Bloom {
+    let mut bloom = [0u8; 256];
+    for log in logs {
+        accrue_bloom(&mut bloom, &log.address);
+        for topic in &log.topics {
+            accrue_bloom(&mut bloom, topic.as_slice());
+        }
+    }
+    bloom
+}
+
+pub fn merge_bloom(bloom: &mut Bloom, other: &Bloom) {
+    for (a, b) in bloom.iter_mut().zip(other.iter()) {
+        *a |= b;
+    }
+}
+
+fn accrue_bloom(bloom: &mut Bloom, input: &[u8]) {
+    let hash = Keccak256::digest(input);
+    for i in [0, 2, 4] {
+        let bit = (((hash[i] as usize) << 8) | hash[i + 1] as usize) & 2047;
+        bloom[255 - bit / 8] |= 1 << (bit % 8);
+    }
+}
+
 #[derive(Debug)]
 pub struct TransactionWithLogs {
     pub tx: Transaction,
     pub logs: Vec<Log>,
+    pub token_transfers: Vec<TokenTransfer>,
+    // Some if the tx calls handleOp of the gasless entrypoint
+    pub gasless: Option<GaslessTransaction>,
+}
+
+// Same as `UserOperation` of the gasless module in api-server
+#[derive(Debug)]
+pub struct UserOperation {
+    pub call_contract: [u8; 20],
+    pub call_data: Vec<u8>,
+    pub call_gas_limit: ethabi::Uint,
+    pub verification_gas_limit: ethabi::Uint,
+    pub max_fee_per_gas: ethabi::Uint,
+    pub max_priority_fee_per_gas: ethabi::Uint,
+    pub paymaster_and_data: Vec<u8>,
+}
+
+impl UserOperation {
+    // The paymaster address is the first 20 bytes of paymasterAndData
+    pub fn paymaster(&self) -> Option<[u8; 20]> {
+        if self.paymaster_and_data.len() < 20 {
+            return None;
+        }
+        let mut paymaster = [0u8; 20];
+        paymaster.copy_from_slice(&self.paymaster_and_data[0..20]);
+        Some(paymaster)
+    }
+}
+
+#[derive(Debug)]
+pub struct GaslessTransaction {
+    pub transaction_hash: H256,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub transaction_index: u32,
+    // signer of the transaction, the entrypoint calls `call_contract` on behalf of it
+    pub sender: [u8; 20],
+    pub entrypoint: [u8; 20],
+    pub user_operation: UserOperation,
+}
+
+// Withdrawal request carried in a L2 block
+#[derive(Debug)]
+pub struct Withdrawal {
+    pub block_number: u64,
+    pub block_hash: H256,
+    // index in block
+    pub withdrawal_index: u32,
+    pub account_script_hash: H256,
+    pub registry_id: u32,
+    // None if the account is not an eth EOA or its script is not found
+    pub registry_address: Option<Address>,
+    pub capacity: u64,
+    pub sudt_script_hash: H256,
+    pub amount: u128,
+    pub owner_lock_hash: H256,
+    pub nonce: u32,
+    pub fee: u128,
+    pub chain_id: u64,
+}
+
+// L2 transaction not indexed as a web3 transaction, e.g. from an unknown or web3 hidden eoa
+// lock, kept in godwoken native format
+#[derive(Debug)]
+pub struct GwTransaction {
+    pub hash: H256,
+    pub block_number: u64,
+    pub block_hash: H256,
+    // index in L2 block, including web3 transactions
+    pub l2_tx_index: u32,
+    pub from_id: u32,
+    pub to_id: u32,
+    pub nonce: u32,
+    pub args: Vec<u8>,
+    // script hash of the sender account, blake2b_256 of its eoa lock script
+    pub from_lock_hash: H256,
+    // None for unknown eoa locks
+    pub eoa_type: Option<String>,
+    pub exit_code: u8,
+}
+
+#[derive(Debug)]
+pub struct BlockWithTransactions {
+    pub block: Block,
+    pub txs: Vec<TransactionWithLogs>,
+    pub withdrawals: Vec<Withdrawal>,
+    pub gw_transactions: Vec<GwTransaction>,
+    // accounts first seen while converting this block, written with it when `persist_accounts` is set
+    pub accounts: Vec<Account>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::from_hex;
+
+    #[test]
+    fn test_polyjuice_status_from_code() {
+        assert_eq!(PolyjuiceStatus::from(0), PolyjuiceStatus::Success);
+        assert_eq!(PolyjuiceStatus::from(2), PolyjuiceStatus::Revert);
+        assert_eq!(PolyjuiceStatus::from(3), PolyjuiceStatus::OutOfGas);
+        assert_eq!(
+            PolyjuiceStatus::from(17),
+            PolyjuiceStatus::InsufficientBalance
+        );
+        assert_eq!(PolyjuiceStatus::from(-1), PolyjuiceStatus::InternalError);
+        assert_eq!(PolyjuiceStatus::from(-3), PolyjuiceStatus::OutOfMemory);
+        assert_eq!(PolyjuiceStatus::from(18), PolyjuiceStatus::Unknown(18));
+        assert_eq!(PolyjuiceStatus::from(-4), PolyjuiceStatus::Unknown(-4));
+    }
+
+    #[test]
+    fn test_polyjuice_status_failure_reason() {
+        assert_eq!(PolyjuiceStatus::Success.failure_reason(), None);
+        assert_eq!(
+            PolyjuiceStatus::Revert.failure_reason().as_deref(),
+            Some("revert")
+        );
+        assert_eq!(
+            PolyjuiceStatus::OutOfGas.failure_reason().as_deref(),
+            Some("out of gas")
+        );
+        assert_eq!(
+            PolyjuiceStatus::Unknown(18).failure_reason().as_deref(),
+            Some("unknown status code 18")
+        );
+    }
+
+    #[test]
+    fn test_decode_error_string() {
+        // `require(balance >= amount, "Not enough tokens")`
+        let return_data = from_hex("0x08c379a0000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000114e6f7420656e6f75676820746f6b656e73000000000000000000000000000000");
+        assert_eq!(
+            decode_revert_reason(&return_data).as_deref(),
+            Some("Not enough tokens")
+        );
+        // truncated string payload
+        assert_eq!(decode_revert_reason(&return_data[..68]), None);
+    }
+
+    #[test]
+    fn test_decode_panic() {
+        // checked arithmetic overflow
+        let return_data =
+            from_hex("0x4e487b710000000000000000000000000000000000000000000000000000000000000011");
+        assert_eq!(
+            decode_revert_reason(&return_data).as_deref(),
+            Some("panic code 0x11 (Arithmetic operation underflowed or overflowed outside of an unchecked block)")
+        );
+        let return_data =
+            from_hex("0x4e487b710000000000000000000000000000000000000000000000000000000000000099");
+        assert_eq!(
+            decode_revert_reason(&return_data).as_deref(),
+            Some("panic code 0x99")
+        );
+        let return_data =
+            from_hex("0x4e487b71ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff");
+        assert_eq!(
+            decode_revert_reason(&return_data).as_deref(),
+            Some("panic code 0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff")
+        );
+    }
+
+    #[test]
+    fn test_decode_unknown_revert_data() {
+        assert_eq!(decode_revert_reason(&[]), None);
+        assert_eq!(decode_revert_reason(&from_hex("0x08c379")), None);
+        // custom error `InsufficientBalance(uint256)`
+        assert_eq!(
+            decode_revert_reason(&from_hex(
+                "0xcf4791810000000000000000000000000000000000000000000000000000000000000001"
+            )),
+            None
+        );
+        // selector only
+        assert_eq!(decode_revert_reason(&from_hex("0x4e487b71")), None);
+    }
+
+    fn log(address: &str, topics: &[&str]) -> Log {
+        let mut log_address = [0u8; 20];
+        log_address.copy_from_slice(&from_hex(address));
+        Log::new(
+            H256::zero(),
+            0,
+            12_000_000,
+            H256::zero(),
+            log_address,
+            vec![],
+            0,
+            topics
+                .iter()
+                .map(|topic| {
+                    let mut hash = [0u8; 32];
+                    hash.copy_from_slice(&from_hex(topic));
+                    hash.into()
+                })
+                .collect(),
+        )
+    }
+
+    fn bloom(value: &str) -> Bloom {
+        let mut bloom = [0u8; 256];
+        bloom.copy_from_slice(&from_hex(value));
+        bloom
+    }
+
+    // Receipt of tx 4 in ethereum mainnet block 12000000, a single LINK transfer
+    fn single_log_receipt() -> (Vec<Log>, Bloom) {
+        let logs = [(
+            "0x514910771AF9Ca656af840dff83E8264EcF986CA",
+            &[
+                "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+                "0x000000000000000000000000d7efcbb86efdd9e8de014dafa5944aae36e817e4",
+                "0x000000000000000000000000ead301653827fd6d2e2c2f16374791b4fb61cc2e",
+            ],
+        )];
+        (
+            logs.iter().map(|(address, topics)| log(address, topics)).collect(),
+            bloom("0x00000000000000000000000000001000000008000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000040000008000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000000000000000000000002000000000000000000000004000000000040000000000000000000000000000000000000000000000000000000000002000000000000001000000000"),
+        )
+    }
+
+    // Receipt of tx 19 in ethereum mainnet block 12000000, a swap with 11 logs
+    fn multi_log_receipt() -> (Vec<Log>, Bloom) {
+        let logs = [
+            (
+                "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                &[
+                    "0xe1fffcc4923d04b559f4d29a8bfc6cda04eb5b0d3c460751c2402c5c5cc9109c",
+                    "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                ],
+            ),
+            (
+                "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                &[
+                    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+                    "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                    "0x000000000000000000000000570febdf89c07f256c75686caca215289bb11cfc",
+                ],
+            ),
+            (
+                "0xBBc2AE13b23d715c30720F079fcd9B4a74093505",
+                &[
+                    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+                    "0x000000000000000000000000570febdf89c07f256c75686caca215289bb11cfc",
+                    "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                ],
+            ),
+            (
+                "0x570fEbDf89C07f256C75686CaCa215289bB11CFc",
+                &["0x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50e062b03a9fffbbad1"],
+            ),
+            (
+                "0x570fEbDf89C07f256C75686CaCa215289bB11CFc",
+                &[
+                    "0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822",
+                    "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                    "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                ],
+            ),
+            (
+                "0xBBc2AE13b23d715c30720F079fcd9B4a74093505",
+                &[
+                    "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925",
+                    "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                    "0x00000000000000000000000095e6f48254609a6ee006f7d493c8e5fb97094cef",
+                ],
+            ),
+            (
+                "0x61935CbDd02287B511119DDb11Aeb42F1593b7Ef",
+                &[
+                    "0x6869791f0a34781b29882982cc39e882768cf2c96995c2a110c577c53bc932d5",
+                    "0x00000000000000000000000044bdb19db1cd29d546597af7dc0549e7f6f9e480",
+                    "0x0000000000000000000000001000000000000000000000000000000000000011",
+                    "0x9ff9816348155d20db8c09e6b34a4ae9f6c20b4d76ae769aec3796376c17dfcd",
+                ],
+            ),
+            (
+                "0xBBc2AE13b23d715c30720F079fcd9B4a74093505",
+                &[
+                    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+                    "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                    "0x00000000000000000000000044bdb19db1cd29d546597af7dc0549e7f6f9e480",
+                ],
+            ),
+            (
+                "0xBBc2AE13b23d715c30720F079fcd9B4a74093505",
+                &[
+                    "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925",
+                    "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                    "0x00000000000000000000000095e6f48254609a6ee006f7d493c8e5fb97094cef",
+                ],
+            ),
+            (
+                "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                &[
+                    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+                    "0x00000000000000000000000044bdb19db1cd29d546597af7dc0549e7f6f9e480",
+                    "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                ],
+            ),
+            (
+                "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                &[
+                    "0x7fcf532c15f0a6db0bd6d0e038bea71d30d808c7d98cb3bf7268a95bf5081b65",
+                    "0x0000000000000000000000000000000000007f150bd6f54c40a34d7c3d5e9f56",
+                ],
+            ),
+        ];
+        (
+            logs.iter().map(|(address, topics)| log(address, topics)).collect(),
+            bloom("0x0020000000100000000020008000040000000000000000000000000000000000000000000000080200000000000000000200000008000040000000000020000000000000000000000000000800000020000000000040000000000000c000010000000000000000000000100000000100000000802000040000002010000000000000000000000000000000000000000000000101000000090001004080000100020000010000000000000004000000000000000000000000001200080000000000000002000000000000102000000000040000000000001000000002000000000010200280000000004000000000000000002000000000400000000000000000"),
+        )
+    }
+
+    #[test]
+    fn test_logs_bloom() {
+        assert_eq!(logs_bloom(&[]), [0u8; 256]);
+
+        let (logs, expected) = single_log_receipt();
+        assert_eq!(logs_bloom(&logs), expected);
+
+        let (logs, expected) = multi_log_receipt();
+        assert_eq!(logs_bloom(&logs), expected);
+    }
+
+    #[test]
+    fn test_merge_bloom() {
+        let (single_logs, single_bloom) = single_log_receipt();
+        let (multi_logs, multi_bloom) = multi_log_receipt();
+
+        // block bloom is the union of the receipt blooms
+        let mut block_bloom = [0u8; 256];
+        merge_bloom(&mut block_bloom, &single_bloom);
+        merge_bloom(&mut block_bloom, &multi_bloom);
+        assert_eq!(
+            block_bloom,
+            logs_bloom(single_logs.iter().chain(multi_logs.iter()))
+        );
+    }
 }
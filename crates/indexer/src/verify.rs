@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::{postgres::PgRow, Row};
+
+use crate::{
+    helper::hex,
+    pool::POOL,
+    types::{Block, BlockWithTransactions, Log, Transaction},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MismatchKind {
+    MissingBlock,
+    BlockHashDivergence,
+    BlockField,
+    MissingTx,
+    UnexpectedTx,
+    TxField,
+    MissingLog,
+    UnexpectedLog,
+    LogField,
+}
+
+impl MismatchKind {
+    // Value of `kind` in the verify report
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MismatchKind::MissingBlock => "missing_block",
+            MismatchKind::BlockHashDivergence => "block_hash_divergence",
+            MismatchKind::BlockField => "block_field",
+            MismatchKind::MissingTx => "missing_tx",
+            MismatchKind::UnexpectedTx => "unexpected_tx",
+            MismatchKind::TxField => "tx_field",
+            MismatchKind::MissingLog => "missing_log",
+            MismatchKind::UnexpectedLog => "unexpected_log",
+            MismatchKind::LogField => "log_field",
+        }
+    }
+}
+
+// A line of the verify report, `expected` is derived from godwoken and `actual` is in the database.
+// Hashes, addresses and bytes are 0x prefixed hex, numbers are decimal strings.
+#[derive(Debug, Serialize)]
+pub struct Mismatch {
+    pub block_number: u64,
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_hash: Option<String>,
+    // position of the log in its transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_position: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<String>,
+}
+
+impl Mismatch {
+    fn new(block_number: u64, kind: MismatchKind) -> Self {
+        Mismatch {
+            block_number,
+            kind: kind.as_str(),
+            transaction_hash: None,
+            log_position: None,
+            field: None,
+            expected: None,
+            actual: None,
+        }
+    }
+}
+
+type Fields = Vec<(&'static str, Option<String>)>;
+
+const BLOCK_COLUMNS: &str = "'0x' || encode(hash, 'hex') AS hash, '0x' || encode(parent_hash, 'hex') AS parent_hash, gas_limit::text AS gas_limit, gas_used::text AS gas_used, (extract(epoch FROM timestamp) * 1000)::bigint::text AS timestamp, '0x' || encode(miner, 'hex') AS miner, size::text AS size, '0x' || encode(logs_bloom, 'hex') AS logs_bloom";
+
+const TX_COLUMNS: &str = "'0x' || encode(hash, 'hex') AS hash, '0x' || encode(eth_tx_hash, 'hex') AS eth_tx_hash, transaction_index::text AS transaction_index, '0x' || encode(from_address, 'hex') AS from_address, '0x' || encode(to_address, 'hex') AS to_address, value::text AS value, nonce::text AS nonce, gas_limit::text AS gas_limit, gas_price::text AS gas_price, '0x' || encode(input, 'hex') AS input, gas_used::text AS gas_used, cumulative_gas_used::text AS cumulative_gas_used, '0x' || encode(contract_address, 'hex') AS contract_address, exit_code::text AS exit_code, status_code::text AS status_code, '0x' || encode(revert_data, 'hex') AS revert_data, '0x' || encode(logs_bloom, 'hex') AS logs_bloom";
+
+const LOG_COLUMNS: &str = "'0x' || encode(transaction_hash, 'hex') AS transaction_hash, transaction_index::text AS transaction_index, log_index::text AS log_index, '0x' || encode(address, 'hex') AS address, '0x' || encode(data, 'hex') AS data, array_to_string(ARRAY(SELECT '0x' || encode(topic, 'hex') FROM unnest(topics) AS topic), ',') AS topics";
+
+// Compare a block derived from godwoken with the rows of `blocks`, `transactions` and `logs`,
+// returns all mismatches, empty if the block is indexed correctly.
+pub async fn verify_block(expected: &BlockWithTransactions) -> Result<Vec<Mismatch>> {
+    let block_number = expected.block.number;
+    let number = Decimal::from(block_number);
+    let mut mismatches = vec![];
+
+    let block_row = sqlx::query(&format!(
+        "SELECT {} FROM blocks WHERE number = $1",
+        BLOCK_COLUMNS
+    ))
+    .bind(number)
+    .fetch_optional(&*POOL)
+    .await?;
+    let block_row = match block_row {
+        Some(row) => row,
+        None => {
+            mismatches.push(Mismatch::new(block_number, MismatchKind::MissingBlock));
+            return Ok(mismatches);
+        }
+    };
+    let expected_hash = hex(expected.block.hash.as_slice())?;
+    let actual_hash: Option<String> = block_row.try_get("hash")?;
+    if actual_hash.as_ref() != Some(&expected_hash) {
+        // other rows of a divergent block belong to another chain, don't compare them
+        let mut mismatch = Mismatch::new(block_number, MismatchKind::BlockHashDivergence);
+        mismatch.field = Some("hash");
+        mismatch.expected = Some(expected_hash);
+        mismatch.actual = actual_hash;
+        mismatches.push(mismatch);
+        return Ok(mismatches);
+    }
+    for (field, expected_value, actual_value) in
+        diff_fields(block_fields(&expected.block)?, &block_row)?
+    {
+        let mut mismatch = Mismatch::new(block_number, MismatchKind::BlockField);
+        mismatch.field = Some(field);
+        mismatch.expected = expected_value;
+        mismatch.actual = actual_value;
+        mismatches.push(mismatch);
+    }
+
+    let tx_rows = sqlx::query(&format!(
+        "SELECT {} FROM transactions WHERE block_number = $1 ORDER BY transaction_index",
+        TX_COLUMNS
+    ))
+    .bind(number)
+    .fetch_all(&*POOL)
+    .await?;
+    let mut tx_rows = tx_rows
+        .into_iter()
+        .map(|row| Ok((row.try_get::<String, _>("hash")?, row)))
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    let log_rows = sqlx::query(&format!(
+        "SELECT {} FROM logs WHERE block_number = $1 ORDER BY log_index",
+        LOG_COLUMNS
+    ))
+    .bind(number)
+    .fetch_all(&*POOL)
+    .await?;
+    let mut log_rows_by_tx: HashMap<String, Vec<PgRow>> = HashMap::new();
+    for row in log_rows {
+        let tx_hash: String = row.try_get("transaction_hash")?;
+        log_rows_by_tx.entry(tx_hash).or_default().push(row);
+    }
+
+    for tx_with_logs in &expected.txs {
+        let tx_hash = hex(tx_with_logs.tx.gw_tx_hash.as_slice())?;
+        let tx_row = match tx_rows.remove(&tx_hash) {
+            Some(row) => row,
+            None => {
+                let mut mismatch = Mismatch::new(block_number, MismatchKind::MissingTx);
+                mismatch.transaction_hash = Some(tx_hash);
+                mismatches.push(mismatch);
+                continue;
+            }
+        };
+        for (field, expected_value, actual_value) in
+            diff_fields(tx_fields(&tx_with_logs.tx)?, &tx_row)?
+        {
+            let mut mismatch = Mismatch::new(block_number, MismatchKind::TxField);
+            mismatch.transaction_hash = Some(tx_hash.clone());
+            mismatch.field = Some(field);
+            mismatch.expected = expected_value;
+            mismatch.actual = actual_value;
+            mismatches.push(mismatch);
+        }
+
+        let log_rows = log_rows_by_tx.remove(&tx_hash).unwrap_or_default();
+        for (log_position, log) in tx_with_logs.logs.iter().enumerate() {
+            let mismatch_of = |kind: MismatchKind| {
+                let mut mismatch = Mismatch::new(block_number, kind);
+                mismatch.transaction_hash = Some(tx_hash.clone());
+                mismatch.log_position = Some(log_position);
+                mismatch
+            };
+            let log_row = match log_rows.get(log_position) {
+                Some(row) => row,
+                None => {
+                    mismatches.push(mismatch_of(MismatchKind::MissingLog));
+                    continue;
+                }
+            };
+            for (field, expected_value, actual_value) in diff_fields(log_fields(log)?, log_row)? {
+                let mut mismatch = mismatch_of(MismatchKind::LogField);
+                mismatch.field = Some(field);
+                mismatch.expected = expected_value;
+                mismatch.actual = actual_value;
+                mismatches.push(mismatch);
+            }
+        }
+        for log_position in tx_with_logs.logs.len()..log_rows.len() {
+            let mut mismatch = Mismatch::new(block_number, MismatchKind::UnexpectedLog);
+            mismatch.transaction_hash = Some(tx_hash.clone());
+            mismatch.log_position = Some(log_position);
+            mismatches.push(mismatch);
+        }
+    }
+
+    // rows left don't exist on godwoken
+    let mut unexpected_txs = tx_rows.into_keys().collect::<Vec<_>>();
+    unexpected_txs.sort();
+    for tx_hash in unexpected_txs {
+        let mut mismatch = Mismatch::new(block_number, MismatchKind::UnexpectedTx);
+        mismatch.transaction_hash = Some(tx_hash);
+        mismatches.push(mismatch);
+    }
+
+    Ok(mismatches)
+}
+
+// Returns (field, expected, actual) of the fields which differ from the row,
+// the row must select every field as text.
+fn diff_fields(
+    expected: Fields,
+    row: &PgRow,
+) -> Result<Vec<(&'static str, Option<String>, Option<String>)>> {
+    let mut diffs = vec![];
+    for (field, expected_value) in expected {
+        let actual_value: Option<String> = row.try_get(field)?;
+        if actual_value != expected_value {
+            diffs.push((field, expected_value, actual_value));
+        }
+    }
+    Ok(diffs)
+}
+
+fn block_fields(block: &Block) -> Result<Fields> {
+    Ok(vec![
+        ("parent_hash", Some(hex(block.parent_hash.as_slice())?)),
+        ("gas_limit", Some(block.gas_limit.to_string())),
+        ("gas_used", Some(block.gas_used.to_string())),
+        (
+            "timestamp",
+            Some(block.timestamp.timestamp_millis().to_string()),
+        ),
+        ("miner", Some(hex(&block.miner)?)),
+        ("size", Some(block.size.to_string())),
+        ("logs_bloom", Some(hex(&block.logs_bloom)?)),
+    ])
+}
+
+fn tx_fields(tx: &Transaction) -> Result<Fields> {
+    Ok(vec![
+        (
+            "eth_tx_hash",
+            Some(hex(tx.compute_eth_tx_hash().as_slice())?),
+        ),
+        ("transaction_index", Some(tx.transaction_index.to_string())),
+        ("from_address", Some(hex(&tx.from_address)?)),
+        (
+            "to_address",
+            tx.to_address.as_ref().map(|addr| hex(addr)).transpose()?,
+        ),
+        ("value", Some(tx.value.to_string())),
+        ("nonce", Some(tx.nonce.to_string())),
+        ("gas_limit", Some(tx.gas_limit.to_string())),
+        ("gas_price", Some(tx.gas_price.to_string())),
+        ("input", Some(hex(&tx.data)?)),
+        ("gas_used", Some(tx.gas_used.to_string())),
+        (
+            "cumulative_gas_used",
+            Some(tx.cumulative_gas_used.to_string()),
+        ),
+        (
+            "contract_address",
+            tx.contract_address
+                .as_ref()
+                .map(|addr| hex(addr))
+                .transpose()?,
+        ),
+        ("exit_code", Some(tx.exit_code.to_string())),
+        ("status_code", tx.status_code.map(|code| code.to_string())),
+        (
+            "revert_data",
+            tx.revert_data.as_ref().map(|data| hex(data)).transpose()?,
+        ),
+        ("logs_bloom", Some(hex(&tx.logs_bloom)?)),
+    ])
+}
+
+fn log_fields(log: &Log) -> Result<Fields> {
+    let topics = log
+        .topics
+        .iter()
+        .map(|topic| hex(topic.as_slice()))
+        .collect::<Result<Vec<_>>>()?
+        .join(",");
+    Ok(vec![
+        ("transaction_index", Some(log.transaction_index.to_string())),
+        ("log_index", Some(log.log_index.to_string())),
+        ("address", Some(hex(&log.address)?)),
+        ("data", Some(hex(&log.data)?)),
+        ("topics", Some(topics)),
+    ])
+}
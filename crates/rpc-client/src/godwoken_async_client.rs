@@ -2,13 +2,21 @@ use anyhow::Result;
 use async_jsonrpc_client::{BatchTransport, HttpClient, Output, Params as ClientParams, Transport};
 use ckb_jsonrpc_types::Script;
 use ckb_types::H256;
-use gw_jsonrpc_types::ckb_jsonrpc_types::Uint32;
+use gw_jsonrpc_types::{
+    ckb_jsonrpc_types::{JsonBytes, Uint128, Uint32, Uint64},
+    debugger::{DumpChallengeTarget, ReprMockTransaction},
+    godwoken::{L2BlockView, L2BlockWithStatus, NodeInfo, RunResult, TxReceipt},
+};
 use itertools::Itertools;
 use serde::de::DeserializeOwned;
 use serde_json::{from_value, json};
 
+use crate::error::RpcClientError;
+
 type AccountID = Uint32;
 
+type RpcClientResult<T> = Result<T, RpcClientError>;
+
 pub struct GodwokenAsyncClient {
     client: HttpClient,
 }
@@ -49,6 +57,168 @@ impl GodwokenAsyncClient {
         Ok(script)
     }
 
+    pub async fn get_tip_block_hash(&self) -> RpcClientResult<Option<H256>> {
+        self.rpc::<Option<H256>>("gw_get_tip_block_hash", None)
+            .await
+    }
+
+    pub async fn get_balance(
+        &self,
+        registry_address: JsonBytes,
+        sudt_id: u32,
+    ) -> RpcClientResult<u128> {
+        self.rpc::<Uint128>(
+            "gw_get_balance",
+            Some(ClientParams::Array(vec![
+                json!(registry_address),
+                json!(AccountID::from(sudt_id)),
+            ])),
+        )
+        .await
+        .map(Into::into)
+    }
+
+    pub async fn get_account_id_by_script_hash(
+        &self,
+        script_hash: H256,
+    ) -> RpcClientResult<Option<u32>> {
+        self.rpc::<Option<Uint32>>(
+            "gw_get_account_id_by_script_hash",
+            Some(ClientParams::Array(vec![json!(script_hash)])),
+        )
+        .await
+        .map(|opt| opt.map(Into::into))
+    }
+
+    pub async fn get_nonce(&self, account_id: u32) -> RpcClientResult<u32> {
+        self.rpc::<Uint32>(
+            "gw_get_nonce",
+            Some(ClientParams::Array(vec![json!(AccountID::from(
+                account_id
+            ))])),
+        )
+        .await
+        .map(Into::into)
+    }
+
+    pub async fn submit_withdrawal_request(
+        &self,
+        withdrawal_request: JsonBytes,
+    ) -> RpcClientResult<()> {
+        self.rpc::<()>(
+            "gw_submit_withdrawal_request",
+            Some(ClientParams::Array(vec![json!(withdrawal_request)])),
+        )
+        .await
+    }
+
+    pub async fn submit_l2transaction(&self, l2tx: JsonBytes) -> RpcClientResult<H256> {
+        self.rpc::<H256>(
+            "gw_submit_l2transaction",
+            Some(ClientParams::Array(vec![json!(l2tx)])),
+        )
+        .await
+    }
+
+    pub async fn execute_l2transaction(&self, l2tx: JsonBytes) -> RpcClientResult<RunResult> {
+        self.rpc::<RunResult>(
+            "gw_execute_l2transaction",
+            Some(ClientParams::Array(vec![json!(l2tx)])),
+        )
+        .await
+    }
+
+    pub async fn execute_raw_l2transaction(
+        &self,
+        raw_l2tx: JsonBytes,
+    ) -> RpcClientResult<RunResult> {
+        self.rpc::<RunResult>(
+            "gw_execute_raw_l2transaction",
+            Some(ClientParams::Array(vec![json!(raw_l2tx)])),
+        )
+        .await
+    }
+
+    pub async fn get_block(&self, block_hash: &H256) -> RpcClientResult<Option<L2BlockWithStatus>> {
+        self.rpc::<Option<L2BlockWithStatus>>(
+            "gw_get_block",
+            Some(ClientParams::Array(vec![json!(block_hash)])),
+        )
+        .await
+    }
+
+    pub async fn get_block_hash(&self, block_number: u64) -> RpcClientResult<Option<H256>> {
+        self.rpc::<Option<H256>>(
+            "gw_get_block_hash",
+            Some(ClientParams::Array(vec![json!(Uint64::from(block_number))])),
+        )
+        .await
+    }
+
+    pub async fn get_node_info(&self) -> RpcClientResult<NodeInfo> {
+        self.rpc::<NodeInfo>("gw_get_node_info", None).await
+    }
+
+    pub async fn debug_dump_cancel_challenge_tx(
+        &self,
+        challenge_target: DumpChallengeTarget,
+    ) -> Result<ReprMockTransaction> {
+        self.request::<ReprMockTransaction>(
+            "debug_dump_cancel_challenge_tx",
+            Some(ClientParams::Array(vec![json!(challenge_target)])),
+        )
+        .await
+    }
+
+    pub async fn get_block_by_number(
+        &self,
+        block_number: u64,
+    ) -> RpcClientResult<Option<L2BlockView>> {
+        self.rpc::<Option<L2BlockView>>(
+            "gw_get_block_by_number",
+            Some(ClientParams::Array(vec![json!(Uint64::from(block_number))])),
+        )
+        .await
+    }
+
+    pub async fn get_transaction_receipt(
+        &self,
+        tx_hash: &H256,
+    ) -> RpcClientResult<Option<TxReceipt>> {
+        self.rpc::<Option<TxReceipt>>(
+            "gw_get_transaction_receipt",
+            Some(ClientParams::Array(vec![json!(tx_hash)])),
+        )
+        .await
+    }
+
+    // Get receipts in one batch request, each entry fails independently.
+    // A receipt comes with the return data of its transaction if godwoken returns it.
+    pub async fn get_transaction_receipt_batch(
+        &self,
+        tx_hashes: &[H256],
+    ) -> RpcClientResult<Vec<Result<Option<(TxReceipt, Option<JsonBytes>)>>>> {
+        let params = tx_hashes
+            .iter()
+            .map(|h| {
+                (
+                    "gw_get_transaction_receipt",
+                    Some(ClientParams::Array(vec![json!(h)])),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let responses = self.client().request_batch(params).await.map_err(|e| {
+            RpcClientError::ConnectionError(
+                format!("gw_get_transaction_receipt batch({})", tx_hashes.len()),
+                anyhow::Error::from(e),
+            )
+        })?;
+
+        let results = responses.into_iter().map(to_receipt_result).collect();
+        Ok(results)
+    }
+
     fn client(&self) -> &HttpClient {
         &self.client
     }
@@ -78,6 +248,19 @@ impl GodwokenAsyncClient {
         Ok(result)
     }
 
+    // Same as `request`, but reports failures as `RpcClientError::ConnectionError`,
+    // so callers can tell them apart and retry.
+    async fn rpc<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Option<ClientParams>,
+    ) -> RpcClientResult<T> {
+        let params_str = format!("{:?}", params);
+        self.request(method, params)
+            .await
+            .map_err(|e| RpcClientError::ConnectionError(format!("{}({})", method, params_str), e))
+    }
+
     async fn request<T: DeserializeOwned>(
         &self,
         method: &str,
@@ -132,3 +315,17 @@ fn to_result<T: DeserializeOwned>(output: Output) -> anyhow::Result<T> {
         Output::Failure(failure) => Err(anyhow::anyhow!("JSONRPC error: {}", failure.error)),
     }
 }
+
+// `return_data` isn't a field of `TxReceipt`, take it out before parsing the receipt
+fn to_receipt_result(output: Output) -> anyhow::Result<Option<(TxReceipt, Option<JsonBytes>)>> {
+    let mut value: serde_json::Value = to_result(output)?;
+    let return_data = match value
+        .as_object_mut()
+        .and_then(|receipt| receipt.remove("return_data"))
+    {
+        Some(return_data) => Some(from_value::<JsonBytes>(return_data)?),
+        None => None,
+    };
+    let receipt: Option<TxReceipt> = from_value(value)?;
+    Ok(receipt.map(|receipt| (receipt, return_data)))
+}
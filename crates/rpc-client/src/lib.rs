@@ -1,4 +1,3 @@
 pub mod convertion;
 pub mod error;
 pub mod godwoken_async_client;
-pub mod godwoken_rpc_client;
@@ -0,0 +1,66 @@
+import { Knex } from "knex";
+
+// Rows rolled back by the indexer during reorgs, only filled when
+// `archive_orphaned_blocks` is enabled in the indexer.
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema
+    .createTable("orphaned_blocks", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.decimal("number", null, 0).notNullable().index();
+      table.binary("hash").notNullable().index();
+      table.binary("parent_hash").notNullable();
+      table.decimal("gas_limit", null, 0).notNullable();
+      table.decimal("gas_used", null, 0).notNullable();
+      table.binary("miner").notNullable();
+      table.integer("size").notNullable();
+      table.timestamp("timestamp").notNullable();
+      // hash of the canonical block at the same height
+      table.binary("replaced_by");
+      table.timestamp("orphaned_at").notNullable().index();
+    })
+    .createTable("orphaned_transactions", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.binary("hash").notNullable().index();
+      table.binary("eth_tx_hash").notNullable().index();
+      table.decimal("block_number", null, 0).notNullable().index();
+      table.binary("block_hash").notNullable();
+      table.integer("transaction_index").notNullable();
+      table.binary("from_address").notNullable();
+      table.binary("to_address");
+      table.decimal("value", 80, 0).notNullable();
+      table.bigInteger("nonce").notNullable();
+      table.decimal("gas_limit", null, 0);
+      table.decimal("gas_price", null, 0);
+      table.binary("input");
+      table.smallint("v").notNullable();
+      table.binary("r").notNullable();
+      table.binary("s").notNullable();
+      table.decimal("cumulative_gas_used", null, 0);
+      table.decimal("gas_used", null, 0);
+      table.binary("contract_address");
+      table.smallint("exit_code").notNullable();
+      table.decimal("chain_id", null, 0).nullable();
+      table.binary("replaced_by");
+      table.timestamp("orphaned_at").notNullable().index();
+    })
+    .createTable("orphaned_logs", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.binary("transaction_hash").notNullable().index();
+      table.integer("transaction_index").notNullable();
+      table.decimal("block_number", null, 0).notNullable().index();
+      table.binary("block_hash").notNullable();
+      table.binary("address").notNullable();
+      table.binary("data");
+      table.integer("log_index").notNullable();
+      table.specificType("topics", "bytea ARRAY").notNullable();
+      table.binary("replaced_by");
+      table.timestamp("orphaned_at").notNullable().index();
+    });
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema
+    .dropTable("orphaned_logs")
+    .dropTable("orphaned_transactions")
+    .dropTable("orphaned_blocks");
+}
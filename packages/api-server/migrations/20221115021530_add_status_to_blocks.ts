@@ -0,0 +1,18 @@
+import { Knex } from "knex";
+
+// Block status on godwoken, updated by the indexer as the chain finalizes
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema.alterTable("blocks", (table) => {
+    table
+      .enu("status", ["unfinalized", "finalized", "reverted"])
+      .notNullable()
+      .defaultTo("unfinalized")
+      .index();
+  });
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema.alterTable("blocks", (table) => {
+    table.dropColumn("status");
+  });
+}
@@ -0,0 +1,15 @@
+import { Knex } from "knex";
+
+// Account scripts fetched by the indexer, rows first seen in rolled back blocks are deleted on reorg
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema.createTable("accounts", (table) => {
+    table.bigInteger("id").primary();
+    table.binary("script_hash").notNullable().unique();
+    table.binary("script").notNullable();
+    table.decimal("first_seen_block_number", null, 0).notNullable().index();
+  });
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema.dropTable("accounts");
+}
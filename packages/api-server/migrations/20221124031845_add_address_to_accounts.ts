@@ -0,0 +1,22 @@
+import { Knex } from "knex";
+
+// Existing rows are cleared since they lack the new columns, the indexer refetches them from godwoken
+export async function up(knex: Knex): Promise<void> {
+  await knex("accounts").del();
+  await knex.schema.alterTable("accounts", (table) => {
+    table.binary("code_hash").notNullable();
+    table
+      .enu("type", ["eth_eoa", "polyjuice_contract", "sudt", "other"])
+      .notNullable()
+      .index();
+    table.binary("eth_address").nullable().index();
+  });
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema.alterTable("accounts", (table) => {
+    table.dropColumn("code_hash");
+    table.dropColumn("type");
+    table.dropColumn("eth_address");
+  });
+}
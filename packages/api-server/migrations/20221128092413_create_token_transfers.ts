@@ -0,0 +1,49 @@
+import { Knex } from "knex";
+
+// SUDT transfer and fee logs, including the transfers inside contract calls
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema
+    .createTable("token_transfers", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.binary("transaction_hash").notNullable().index();
+      table.integer("transaction_index").notNullable();
+      table.decimal("block_number", null, 0).notNullable().index();
+      table.binary("block_hash").notNullable();
+      // index in block, like logs.log_index
+      table.integer("transfer_index").notNullable();
+      table.bigInteger("sudt_id").notNullable().index();
+      table.bigInteger("from_registry_id").notNullable();
+      table.binary("from_address").notNullable().index();
+      table.bigInteger("to_registry_id").notNullable();
+      table.binary("to_address").notNullable().index();
+      table.decimal("amount", 80, 0).notNullable();
+      table.enu("kind", ["transfer", "fee"]).notNullable();
+      table.unique(["block_number", "transfer_index"]);
+    })
+    .createTable(
+      "orphaned_token_transfers",
+      function (table: Knex.TableBuilder) {
+        table.bigIncrements("id");
+        table.binary("transaction_hash").notNullable().index();
+        table.integer("transaction_index").notNullable();
+        table.decimal("block_number", null, 0).notNullable().index();
+        table.binary("block_hash").notNullable();
+        table.integer("transfer_index").notNullable();
+        table.bigInteger("sudt_id").notNullable();
+        table.bigInteger("from_registry_id").notNullable();
+        table.binary("from_address").notNullable();
+        table.bigInteger("to_registry_id").notNullable();
+        table.binary("to_address").notNullable();
+        table.decimal("amount", 80, 0).notNullable();
+        table.enu("kind", ["transfer", "fee"]).notNullable();
+        table.binary("replaced_by");
+        table.timestamp("orphaned_at").notNullable().index();
+      }
+    );
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema
+    .dropTable("orphaned_token_transfers")
+    .dropTable("token_transfers");
+}
@@ -0,0 +1,43 @@
+import { Knex } from "knex";
+
+// Standard token events decoded from logs by the indexer
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema
+    .createTable("erc_transfers", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.binary("transaction_hash").notNullable().index();
+      table.integer("transaction_index").notNullable();
+      table.decimal("block_number", null, 0).notNullable().index();
+      table.binary("block_hash").notNullable();
+      table.integer("log_index").notNullable();
+      // index in TransferBatch, 0 for other events
+      table.integer("batch_index").notNullable();
+      table.enu("standard", ["erc20", "erc721", "erc1155"]).notNullable();
+      table.binary("token_contract").notNullable().index();
+      table.binary("operator");
+      table.binary("from_address").notNullable().index();
+      table.binary("to_address").notNullable().index();
+      table.decimal("token_id", 80, 0);
+      table.decimal("amount", 80, 0).notNullable();
+      table.unique(["block_number", "log_index", "batch_index"]);
+    })
+    .createTable("erc_approvals", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.binary("transaction_hash").notNullable().index();
+      table.integer("transaction_index").notNullable();
+      table.decimal("block_number", null, 0).notNullable().index();
+      table.binary("block_hash").notNullable();
+      table.integer("log_index").notNullable();
+      table.enu("standard", ["erc20", "erc721"]).notNullable();
+      table.binary("token_contract").notNullable().index();
+      table.binary("owner").notNullable().index();
+      table.binary("spender").notNullable().index();
+      table.decimal("token_id", 80, 0);
+      table.decimal("amount", 80, 0);
+      table.unique(["block_number", "log_index"]);
+    });
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema.dropTable("erc_approvals").dropTable("erc_transfers");
+}
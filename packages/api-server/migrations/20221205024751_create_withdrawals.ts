@@ -0,0 +1,48 @@
+import { Knex } from "knex";
+
+// Withdrawal requests carried in L2 blocks
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema
+    .createTable("withdrawals", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.decimal("block_number", null, 0).notNullable().index();
+      table.binary("block_hash").notNullable();
+      table.integer("withdrawal_index").notNullable();
+      table.binary("account_script_hash").notNullable().index();
+      table.bigInteger("registry_id").notNullable();
+      // eth address of the withdrawer, null if unknown
+      table.binary("registry_address").index();
+      table.decimal("capacity", 20, 0).notNullable();
+      table.binary("sudt_script_hash").notNullable();
+      table.decimal("amount", 40, 0).notNullable();
+      table.binary("owner_lock_hash").notNullable().index();
+      table.bigInteger("nonce").notNullable();
+      table.decimal("fee", 40, 0).notNullable();
+      table.decimal("chain_id", 20, 0).notNullable();
+      table.unique(["block_number", "withdrawal_index"]);
+    })
+    .createTable("orphaned_withdrawals", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.decimal("block_number", null, 0).notNullable().index();
+      table.binary("block_hash").notNullable();
+      table.integer("withdrawal_index").notNullable();
+      table.binary("account_script_hash").notNullable();
+      table.bigInteger("registry_id").notNullable();
+      table.binary("registry_address");
+      table.decimal("capacity", 20, 0).notNullable();
+      table.binary("sudt_script_hash").notNullable();
+      table.decimal("amount", 40, 0).notNullable();
+      table.binary("owner_lock_hash").notNullable();
+      table.bigInteger("nonce").notNullable();
+      table.decimal("fee", 40, 0).notNullable();
+      table.decimal("chain_id", 20, 0).notNullable();
+      table.binary("replaced_by");
+      table.timestamp("orphaned_at").notNullable().index();
+    });
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema
+    .dropTable("orphaned_withdrawals")
+    .dropTable("withdrawals");
+}
@@ -0,0 +1,21 @@
+import { Knex } from "knex";
+
+// evmc status code from the polyjuice system log and its failure reason,
+// both null for non-polyjuice transactions
+export async function up(knex: Knex): Promise<void> {
+  for (const tableName of ["transactions", "orphaned_transactions"]) {
+    await knex.schema.alterTable(tableName, (table) => {
+      table.integer("status_code").nullable();
+      table.text("failure_reason").nullable();
+    });
+  }
+}
+
+export async function down(knex: Knex): Promise<void> {
+  for (const tableName of ["transactions", "orphaned_transactions"]) {
+    await knex.schema.alterTable(tableName, (table) => {
+      table.dropColumn("status_code");
+      table.dropColumn("failure_reason");
+    });
+  }
+}
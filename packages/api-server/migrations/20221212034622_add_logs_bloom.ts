@@ -0,0 +1,28 @@
+import { Knex } from "knex";
+
+// 256 bytes logs bloom, null for rows indexed before this migration until they are updated
+export async function up(knex: Knex): Promise<void> {
+  for (const tableName of [
+    "blocks",
+    "transactions",
+    "orphaned_blocks",
+    "orphaned_transactions",
+  ]) {
+    await knex.schema.alterTable(tableName, (table) => {
+      table.binary("logs_bloom").nullable();
+    });
+  }
+}
+
+export async function down(knex: Knex): Promise<void> {
+  for (const tableName of [
+    "blocks",
+    "transactions",
+    "orphaned_blocks",
+    "orphaned_transactions",
+  ]) {
+    await knex.schema.alterTable(tableName, (table) => {
+      table.dropColumn("logs_bloom");
+    });
+  }
+}
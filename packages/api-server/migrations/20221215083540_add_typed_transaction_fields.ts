@@ -0,0 +1,25 @@
+import { Knex } from "knex";
+
+// EIP-2718 transaction type, access list json of EIP-2930 / EIP-1559 transactions
+// and max fee fields of EIP-1559 transactions
+export async function up(knex: Knex): Promise<void> {
+  for (const tableName of ["transactions", "orphaned_transactions"]) {
+    await knex.schema.alterTable(tableName, (table) => {
+      table.smallint("type").notNullable().defaultTo(0);
+      table.text("access_list").nullable();
+      table.decimal("max_priority_fee_per_gas", null, 0).nullable();
+      table.decimal("max_fee_per_gas", null, 0).nullable();
+    });
+  }
+}
+
+export async function down(knex: Knex): Promise<void> {
+  for (const tableName of ["transactions", "orphaned_transactions"]) {
+    await knex.schema.alterTable(tableName, (table) => {
+      table.dropColumn("type");
+      table.dropColumn("access_list");
+      table.dropColumn("max_priority_fee_per_gas");
+      table.dropColumn("max_fee_per_gas");
+    });
+  }
+}
@@ -0,0 +1,22 @@
+import { Knex } from "knex";
+
+// Transactions whose signature doesn't recover to from_address, found by the indexer
+// when `verify_signatures` is enabled or by the audit command
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema.createTable("anomalies", (table) => {
+    table.bigIncrements("id");
+    table.enu("kind", ["invalid_signature", "signer_mismatch"]).notNullable();
+    table.binary("transaction_hash").notNullable().index();
+    table.binary("eth_tx_hash").notNullable();
+    table.decimal("block_number", null, 0).notNullable().index();
+    table.integer("transaction_index").notNullable();
+    table.binary("from_address").notNullable();
+    table.binary("recovered_address");
+    table.text("detail");
+    table.timestamp("detected_at").notNullable().defaultTo(knex.fn.now());
+  });
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema.dropTable("anomalies");
+}
@@ -0,0 +1,44 @@
+import { Knex } from "knex";
+
+// L2 transactions from unknown or web3 hidden EOA locks, kept in godwoken native format
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema
+    .createTable("gw_transactions", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.binary("hash").notNullable().unique();
+      table.decimal("block_number", null, 0).notNullable().index();
+      table.binary("block_hash").notNullable();
+      // index in L2 block, including web3 transactions
+      table.integer("l2_tx_index").notNullable();
+      table.bigInteger("from_id").notNullable().index();
+      table.bigInteger("to_id").notNullable();
+      table.bigInteger("nonce").notNullable();
+      table.binary("args").notNullable();
+      table.binary("from_lock_hash").notNullable();
+      // null for unknown EOA locks
+      table.text("eoa_type");
+      table.smallint("exit_code").notNullable();
+    })
+    .createTable("orphaned_gw_transactions", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.binary("hash").notNullable().index();
+      table.decimal("block_number", null, 0).notNullable().index();
+      table.binary("block_hash").notNullable();
+      table.integer("l2_tx_index").notNullable();
+      table.bigInteger("from_id").notNullable();
+      table.bigInteger("to_id").notNullable();
+      table.bigInteger("nonce").notNullable();
+      table.binary("args").notNullable();
+      table.binary("from_lock_hash").notNullable();
+      table.text("eoa_type");
+      table.smallint("exit_code").notNullable();
+      table.binary("replaced_by");
+      table.timestamp("orphaned_at").notNullable().index();
+    });
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema
+    .dropTable("orphaned_gw_transactions")
+    .dropTable("gw_transactions");
+}
@@ -0,0 +1,54 @@
+import { Knex } from "knex";
+
+// Polyjuice transactions calling handleOp of the gasless entrypoint, the UserOperation
+// decoded from the input and its sponsorship
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema
+    .createTable("gasless_transactions", function (table: Knex.TableBuilder) {
+      table.bigIncrements("id");
+      table.binary("transaction_hash").notNullable().unique();
+      table.decimal("block_number", null, 0).notNullable().index();
+      table.binary("block_hash").notNullable();
+      table.integer("transaction_index").notNullable();
+      // signer of the transaction, gas is paid by the entrypoint / paymaster
+      table.binary("sender").notNullable().index();
+      table.binary("entrypoint").notNullable();
+      table.binary("call_contract").notNullable().index();
+      table.binary("call_data").notNullable();
+      table.decimal("call_gas_limit", 80, 0).notNullable();
+      table.decimal("verification_gas_limit", 80, 0).notNullable();
+      table.decimal("max_fee_per_gas", 80, 0).notNullable();
+      table.decimal("max_priority_fee_per_gas", 80, 0).notNullable();
+      // first 20 bytes of paymasterAndData, null if no paymaster
+      table.binary("paymaster").index();
+      table.binary("paymaster_and_data").notNullable();
+    })
+    .createTable(
+      "orphaned_gasless_transactions",
+      function (table: Knex.TableBuilder) {
+        table.bigIncrements("id");
+        table.binary("transaction_hash").notNullable().index();
+        table.decimal("block_number", null, 0).notNullable().index();
+        table.binary("block_hash").notNullable();
+        table.integer("transaction_index").notNullable();
+        table.binary("sender").notNullable();
+        table.binary("entrypoint").notNullable();
+        table.binary("call_contract").notNullable();
+        table.binary("call_data").notNullable();
+        table.decimal("call_gas_limit", 80, 0).notNullable();
+        table.decimal("verification_gas_limit", 80, 0).notNullable();
+        table.decimal("max_fee_per_gas", 80, 0).notNullable();
+        table.decimal("max_priority_fee_per_gas", 80, 0).notNullable();
+        table.binary("paymaster");
+        table.binary("paymaster_and_data").notNullable();
+        table.binary("replaced_by");
+        table.timestamp("orphaned_at").notNullable().index();
+      }
+    );
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema
+    .dropTable("orphaned_gasless_transactions")
+    .dropTable("gasless_transactions");
+}
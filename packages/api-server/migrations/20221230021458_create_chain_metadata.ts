@@ -0,0 +1,20 @@
+import { Knex } from "knex";
+
+// Identity of the chain indexed into the database, a single row written by the
+// indexer on its first run and checked against godwoken on every start
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema.createTable("chain_metadata", (table) => {
+    table.smallint("id").primary();
+    table.binary("rollup_type_hash").notNullable();
+    table.decimal("chain_id", 20, 0).notNullable();
+    table.binary("genesis_block_hash").notNullable();
+    table.binary("l2_sudt_type_script_hash").notNullable();
+    table.binary("polyjuice_type_script_hash").notNullable();
+    table.binary("eth_account_lock_hash").notNullable();
+    table.timestamp("created_at").notNullable().defaultTo(knex.fn.now());
+  });
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema.dropTable("chain_metadata");
+}
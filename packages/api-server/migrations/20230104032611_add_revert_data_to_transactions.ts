@@ -0,0 +1,19 @@
+import { Knex } from "knex";
+
+// Return data of a failed polyjuice transaction, the `Error(string)` or
+// `Panic(uint256)` payload, null when godwoken doesn't return it
+export async function up(knex: Knex): Promise<void> {
+  for (const tableName of ["transactions", "orphaned_transactions"]) {
+    await knex.schema.alterTable(tableName, (table) => {
+      table.binary("revert_data").nullable();
+    });
+  }
+}
+
+export async function down(knex: Knex): Promise<void> {
+  for (const tableName of ["transactions", "orphaned_transactions"]) {
+    await knex.schema.alterTable(tableName, (table) => {
+      table.dropColumn("revert_data");
+    });
+  }
+}
@@ -0,0 +1,15 @@
+import { Knex } from "knex";
+
+// EOA locks of the chain in chain_metadata, recorded by the indexer with the
+// chain metadata and checked against godwoken on every start
+export async function up(knex: Knex): Promise<void> {
+  await knex.schema.createTable("chain_eoa_locks", (table) => {
+    table.text("eoa_type").primary();
+    table.binary("type_hash").notNullable();
+    table.timestamp("created_at").notNullable().defaultTo(knex.fn.now());
+  });
+}
+
+export async function down(knex: Knex): Promise<void> {
+  await knex.schema.dropTable("chain_eoa_locks");
+}
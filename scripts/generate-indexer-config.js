@@ -88,6 +88,13 @@ const run = async () => {
     }
   }
 
+  // all EOA locks of the rollup, so that the indexer can start before godwoken
+  tomlStr += "\n[eoa_lock_hashes]\n";
+  for (const eoaScript of nodeInfo.eoa_scripts) {
+    console.log(`[eoa_lock_hashes.${eoaScript.eoa_type}]: ${eoaScript.type_hash}`);
+    tomlStr += `${eoaScript.eoa_type}="${eoaScript.type_hash}"\n`;
+  }
+
   const outputPath = path.join(__dirname, "../indexer-config.toml");
   fs.writeFileSync(outputPath, tomlStr);
 };